@@ -21,7 +21,7 @@ for file in files:
         print('No data for ' + typ)
         continue
 
-    wingspan = float(wingspan.text.split(" ")[0]) * 3.28084
-    length = float(length.text.split(" ")[0]) * 3.28084
+    wingspan = wingspan.text.split(" ")[0]
+    length = length.text.split(" ")[0]
 
-    print(f'{typ} = {{ f = "{join(path,file)}", attr = "VATSIM-Radar", l = {length}, w = {wingspan}, optimizer = {{ t = "ad_floor", a_floor = 0.15, d_floor = 0.2 }}}}')
\ No newline at end of file
+    print(f'{typ} = {{ f = "{join(path,file)}", attr = "VATSIM-Radar", l = "{length} m", w = "{wingspan} m", optimizer = {{ t = "ad_floor", a_floor = 0.15, d_floor = 0.2 }}}}')
\ No newline at end of file
@@ -8,4 +8,39 @@ pub fn quad(p0: P, p1: P, p2: P, t: f64) -> P {
 pub fn cubic(p0: P, p1: P, p2: P, p3: P, t: f64) -> P {
     assert!((0.0..=1.0).contains(&t), "cubic() t out of bounds");
     (1.0-t).powi(3)*p0+3.0*(1.0-t).powi(2)*t*p1+3.0*(1.0-t)*t.powi(2)*p2+t.powi(3)*p3
-}
\ No newline at end of file
+}
+
+/// Maximum number of times a curve is split in half while flattening
+const MAX_DEPTH: u32 = 16;
+
+/// Approximate a quadratic curve with line segments no further than `tolerance` from it, pushing every
+/// point after `p0`. Tight bends get more points than gentle ones.
+pub fn flatten_quad(p0: P, p1: P, p2: P, tolerance: f64, out: &mut Vec<P>) {
+    flatten_cubic(p0, p0 + 2.0 / 3.0 * (p1 - p0), p2 + 2.0 / 3.0 * (p1 - p2), p2, tolerance, out);
+}
+
+/// Approximate a cubic curve with line segments no further than `tolerance` from it, pushing every
+/// point after `p0`. Tight bends get more points than gentle ones.
+pub fn flatten_cubic(p0: P, p1: P, p2: P, p3: P, tolerance: f64, out: &mut Vec<P>) {
+    subdivide(p0, p1, p2, p3, tolerance, 0, out);
+}
+
+fn subdivide(p0: P, p1: P, p2: P, p3: P, tolerance: f64, depth: u32, out: &mut Vec<P>) {
+    // the curve lies within the hull of its control points, so it's flat enough once they're close to the chord
+    let flat = p1.distance_to_segment(&p0, &p3) <= tolerance && p2.distance_to_segment(&p0, &p3) <= tolerance;
+    if flat || depth >= MAX_DEPTH {
+        out.push(p3);
+        return;
+    }
+
+    // de Casteljau split at t = 0.5
+    let q0 = 0.5 * (p0 + p1);
+    let q1 = 0.5 * (p1 + p2);
+    let q2 = 0.5 * (p2 + p3);
+    let r0 = 0.5 * (q0 + q1);
+    let r1 = 0.5 * (q1 + q2);
+    let mid = 0.5 * (r0 + r1);
+
+    subdivide(p0, q0, r0, mid, tolerance, depth + 1, out);
+    subdivide(mid, r1, q2, p3, tolerance, depth + 1, out);
+}
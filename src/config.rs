@@ -1,50 +1,613 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, InlineTable, Item, Value};
+use crate::dims::Length;
+use crate::error::{Error, ErrorKind};
 use crate::point::P;
+use crate::select::glob;
 
-#[derive(Serialize)]
+/// The JSON file Tower View reads for one or more aircraft types
+#[derive(Serialize, Deserialize)]
 #[allow(clippy::module_name_repetitions)]
 pub struct AircraftPointFile {
+    /// Outline in feet, centred on the aircraft's origin with the nose towards +y
     pub points: Vec<P>,
     #[serde(rename = "aircraftTypes")]
     pub aircraft_types: Vec<String>,
     pub attribution: String
 }
 
+/// A whole configuration file: `[aircraft]` keyed by type designator, and `[configuration]`.
+///
+/// An optional `[defaults]` table holds fields applied to every aircraft that doesn't set them, and an
+/// aircraft with `extends = "B738"` starts from that aircraft's settings instead. Aircraft may also be split
+/// across other files with a top-level `include = ["aircraft/*.toml"]`, each holding only an `[aircraft]`
+/// table. All of these are resolved by [`ProgramConfig::parse`].
+///
+/// `[profiles.<name>]` tables describe extra builds of every aircraft with another point budget or optimizer,
+/// see [`ProgramConfig::profile`].
 #[derive(Deserialize, Serialize, Clone)]
 #[allow(clippy::module_name_repetitions)]
 pub struct ProgramConfig {
     pub aircraft: HashMap<String, AircraftConfig>,
-    pub configuration: ProgramConfigInner
+    pub configuration: ProgramConfigInner,
+    /// Named output profiles, the `[profiles.<name>]` tables
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Files the configuration was read from, the main one first and then every included one
+    #[serde(skip)]
+    pub files: Vec<PathBuf>
 }
 
+impl ProgramConfig {
+    /// Read and parse a TOML configuration file, along with any files it includes.
+    ///
+    /// # Errors
+    /// If a file can't be read or the configuration isn't valid.
+    pub fn load(file: &Path) -> anyhow::Result<Self> {
+        let s = fs::read_to_string(file)
+            .with_context(|| Error::new(ErrorKind::Io, format!("error reading configuration at {}", file.display())))?;
+        Self::parse(&s, file)
+            .with_context(|| Error::new(ErrorKind::Config, format!("error parsing configuration at {}", file.display())))
+    }
+
+    /// Parse a configuration read from `file`, reading the files it includes relative to it and filling each
+    /// aircraft in from `[defaults]` and the aircraft it `extends`.
+    ///
+    /// # Errors
+    /// If it or an included file isn't a valid configuration, an aircraft is defined twice, or an `extends`
+    /// names an unknown aircraft or goes round in a loop.
+    pub fn parse(s: &str, file: &Path) -> anyhow::Result<Self> {
+        let mut doc = s.parse::<DocumentMut>()?;
+        let defaults = match doc.remove("defaults") {
+            Some(d) => fields(&d).with_context(|| Error::new(ErrorKind::Config, "`defaults` must be a table"))?,
+            None => Fields::new()
+        };
+        let configuration = doc.remove("configuration")
+            .and_then(|c| fields(&c))
+            .with_context(|| Error::new(ErrorKind::Config, "missing `[configuration]` table"))?;
+        let configuration = deserialize(configuration)
+            .with_context(|| Error::new(ErrorKind::Config, "invalid `[configuration]`"))?;
+
+        let profiles: BTreeMap<String, Profile> = match doc.remove("profiles") {
+            Some(p) => deserialize(fields(&p).with_context(|| Error::new(ErrorKind::Config, "`profiles` must be a table"))?)
+                .with_context(|| Error::new(ErrorKind::Config, "invalid `[profiles]`"))?,
+            None => BTreeMap::new()
+        };
+        if let Some(name) = profiles.keys().find(|n| n.is_empty() || !n.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')) {
+            bail!(Error::new(ErrorKind::Config, format!("profile name `{name}` may only contain letters, digits, `-` and `_`, as it's used as a directory name")));
+        }
+
+        let mut files = vec![file.to_path_buf()];
+        let mut entries = Entries::new();
+        add(&mut entries, doc.remove("aircraft"), file)?;
+        for included in includes(doc.remove("include"), file)? {
+            let s = fs::read_to_string(&included)
+                .with_context(|| Error::new(ErrorKind::Io, format!("error reading included configuration at {}", included.display())))?;
+            let mut d = s.parse::<DocumentMut>()
+                .with_context(|| Error::new(ErrorKind::Config, format!("error parsing included configuration at {}", included.display())))?;
+            if let Some((k, _)) = d.iter().find(|(k, _)| *k != "aircraft") {
+                bail!(Error::new(ErrorKind::Config, format!("{} may only contain `[aircraft]`, found `{k}`", included.display())));
+            }
+            add(&mut entries, d.remove("aircraft"), &included)?;
+            files.push(included);
+        }
+
+        let mut aircraft = HashMap::new();
+        for (id, fields) in inherit(&entries, &defaults)? {
+            let c: AircraftConfig = deserialize(fields)
+                .with_context(|| aircraft_error(&id, format!("invalid configuration in {}", entries[&id].1.display())))?;
+            if let Some(name) = c.profiles.keys().find(|n| !profiles.contains_key(*n)) {
+                bail!(aircraft_error(&id, format!("has settings for profile `{name}` in {}, which is not defined", entries[&id].1.display())));
+            }
+            aircraft.insert(id, c);
+        }
+
+        Ok(ProgramConfig { aircraft, configuration, profiles, files })
+    }
+
+    /// The configuration for building a profile: each aircraft's optimizer and point budget replaced by the
+    /// ones it sets for the profile, or else the profile's, and output written to `<output_directory>/<name>`.
+    ///
+    /// # Errors
+    /// If there's no profile called `name`.
+    pub fn profile(&self, name: &str) -> anyhow::Result<ProgramConfig> {
+        let Some(profile) = self.profiles.get(name) else {
+            bail!(Error::new(ErrorKind::Config, format!("no profile named `{name}`, expected one of {}", self.profiles.keys().cloned().collect::<Vec<_>>().join(", "))));
+        };
+
+        let mut config = self.clone();
+        config.configuration.output_directory = self.configuration.output_directory.join(name);
+        for a in config.aircraft.values_mut() {
+            let own = a.profiles.get(name);
+            if let Some(optimizer) = own.and_then(|o| o.optimizer.as_ref()).or(profile.optimizer.as_ref()) {
+                a.optimizer = optimizer.clone();
+            }
+            if let Some(max_points) = own.and_then(|o| o.max_points).or(profile.max_points) {
+                a.max_points = Some(max_points);
+            }
+        }
+        Ok(config)
+    }
+}
+
+type Fields = BTreeMap<String, Value>;
+
+/// Aircraft entries as written, with the file each was defined in
+type Entries = BTreeMap<String, (Fields, PathBuf)>;
+
+fn aircraft_error(id: &str, message: String) -> Error {
+    Error { kind: ErrorKind::Config, aircraft: Some(id.to_string()), file: None, message }
+}
+
+fn fields(item: &Item) -> Option<Fields> {
+    Some(item.as_table_like()?
+        .iter()
+        .filter_map(|(k, v)| Some((k.to_string(), v.clone().into_value().ok()?)))
+        .collect())
+}
+
+fn deserialize<T: DeserializeOwned>(fields: Fields) -> anyhow::Result<T> {
+    let table = fields.into_iter().collect::<InlineTable>().into_table();
+    Ok(toml_edit::de::from_document(DocumentMut::from(table))?)
+}
+
+/// Add the entries of an `[aircraft]` table defined in `file`, rejecting IDs that are already defined
+fn add(entries: &mut Entries, aircraft: Option<Item>, file: &Path) -> anyhow::Result<()> {
+    let Some(aircraft) = aircraft else {
+        return Ok(());
+    };
+    let aircraft = aircraft.as_table_like()
+        .with_context(|| Error::new(ErrorKind::Config, format!("`aircraft` in {} must be a table", file.display())))?;
+
+    for (id, a) in aircraft.iter() {
+        let fields = fields(a).with_context(|| aircraft_error(id, format!("must be a table, in {}", file.display())))?;
+        if let Some((_, first)) = entries.get(id) {
+            bail!(aircraft_error(id, format!("defined in both {} and {}", first.display(), file.display())));
+        }
+        entries.insert(id.to_string(), (fields, file.to_path_buf()));
+    }
+    Ok(())
+}
+
+/// Files named by `include` relative to `file`, in the order given with each pattern's matches sorted
+fn includes(include: Option<Item>, file: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let Some(include) = include else {
+        return Ok(vec![]);
+    };
+    let patterns = include.as_array()
+        .and_then(|a| a.iter().map(Value::as_str).collect::<Option<Vec<_>>>())
+        .with_context(|| Error::new(ErrorKind::Config, "`include` must be an array of paths"))?;
+
+    let dir = file.parent().unwrap_or(Path::new(""));
+    let mut files: Vec<PathBuf> = vec![];
+    for f in patterns.into_iter().flat_map(|p| expand(&dir.join(p))) {
+        if !files.contains(&f) {
+            files.push(f);
+        }
+    }
+    Ok(files)
+}
+
+/// Files matching a path where any component may use `*` and `?`. A path without wildcards is returned
+/// as is, so a missing file is reported when it's read.
+fn expand(pattern: &Path) -> Vec<PathBuf> {
+    if !pattern.to_string_lossy().contains(['*', '?']) {
+        return vec![pattern.to_path_buf()];
+    }
+
+    let mut paths = vec![PathBuf::new()];
+    for c in pattern.components() {
+        let c = c.as_os_str();
+        let name = c.to_string_lossy();
+        if !name.contains(['*', '?']) {
+            for p in &mut paths {
+                p.push(c);
+            }
+            continue;
+        }
+
+        let mut matched = vec![];
+        for p in paths {
+            let dir = if p.as_os_str().is_empty() { Path::new(".") } else { p.as_path() };
+            let Ok(entries) = fs::read_dir(dir) else { continue };
+            for e in entries.flatten() {
+                let n = e.file_name();
+                let n = n.to_string_lossy();
+                // like a shell, wildcards don't match hidden files
+                if (!n.starts_with('.') || name.starts_with('.')) && glob(&name, &n) {
+                    matched.push(p.join(e.file_name()));
+                }
+            }
+        }
+        paths = matched;
+    }
+
+    paths.retain(|p| p.is_file());
+    paths.sort();
+    paths
+}
+
+/// Fill each aircraft entry in from `defaults`, or from the entry named by its `extends`. Fields an entry
+/// sets replace inherited ones whole, so an `optimizer` table is never partly inherited.
+fn inherit(entries: &Entries, defaults: &Fields) -> anyhow::Result<BTreeMap<String, Fields>> {
+    let mut resolved = BTreeMap::new();
+    for id in entries.keys() {
+        resolve(id, entries, defaults, &mut resolved, &mut vec![])?;
+    }
+    Ok(resolved)
+}
+
+fn resolve(id: &str, entries: &Entries, defaults: &Fields, resolved: &mut BTreeMap<String, Fields>, chain: &mut Vec<String>) -> anyhow::Result<Fields> {
+    if let Some(f) = resolved.get(id) {
+        return Ok(f.clone());
+    }
+    if chain.iter().any(|c| c == id) {
+        chain.push(id.to_string());
+        bail!(aircraft_error(id, format!("`extends` goes round in a loop: {}", chain.join(" -> "))));
+    }
+
+    let (own, file) = &entries[id];
+    let mut fields = match own.get("extends") {
+        Some(parent) => {
+            let Some(parent) = parent.as_str() else {
+                bail!(aircraft_error(id, format!("`extends` in {} must be an aircraft ID", file.display())));
+            };
+            if !entries.contains_key(parent) {
+                bail!(aircraft_error(id, format!("extends `{parent}` in {}, which is not configured", file.display())));
+            }
+            chain.push(id.to_string());
+            let inherited = resolve(parent, entries, defaults, resolved, chain)?;
+            chain.pop();
+            inherited
+        },
+        None => defaults.clone()
+    };
+    fields.extend(own.iter().filter(|(k, _)| *k != "extends").map(|(k, v)| (k.clone(), v.clone())));
+
+    resolved.insert(id.to_string(), fields.clone());
+    Ok(fields)
+}
+
+/// How to turn one SVG into an outline
 #[derive(Deserialize, Serialize, Clone)]
 #[allow(clippy::module_name_repetitions)]
 pub struct AircraftConfig {
+    /// SVG file, relative to the working directory
     pub f: PathBuf,
+    /// Attribution written to the output
     pub attr: String,
-    pub w: f64,
-    pub l: f64,
+    /// Wingspan, taken from the built-in table if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub w: Option<Length>,
+    /// Length, taken from the built-in table if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l: Option<Length>,
+    /// How the outline is simplified
     pub optimizer: Optimizer,
+    /// How the SVG is mapped onto the configured wingspan and length
+    #[serde(default, skip_serializing_if = "Scale::is_canvas")]
+    pub scale: Scale,
+    /// Point in the SVG that becomes (0, 0) in the output. Defaults to the canvas centre for `canvas`
+    /// scaling and the path's bounding box centre otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<Origin>,
+    /// Clockwise rotation in degrees needed to point the nose up, or `"auto"` to guess it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotate: Option<Rotation>,
+    /// Mirror the art left-to-right before rotating
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub flip_x: bool,
+    /// Mirror the art top-to-bottom before rotating
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub flip_y: bool,
+    /// Optimize one half of the outline and mirror it, for left/right symmetric aircraft
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symmetric: Option<Symmetry>,
+    /// Keep sharp corners and extreme points regardless of what the optimizer thinks of them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin: Option<Pin>,
+    /// Flatten curves adaptively, keeping within this many SVG units of the true curve. If unset each
+    /// curve is sampled at its start, middle and end
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flatness: Option<f64>,
+    /// Outline the path's stroke using its width, joins and caps, for art that draws thin parts such as
+    /// probes or rotor blades as thick lines
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub outline_stroke: bool,
+    /// Outlines with more points than this after optimizing are an error, instead of the shared `max_points`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_points: Option<usize>,
+    /// Free-form labels such as `military` or `heavy`, for selecting groups of aircraft to build
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Settings for this aircraft in named output profiles, replacing the profile's own
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A named variant of the output, e.g. a low-detail build, written to its own subdirectory of
+/// `output_directory`. Anything left unset keeps the aircraft's usual setting.
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct Profile {
+    /// Point budget, replacing both the shared and the aircraft's `max_points`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_points: Option<usize>,
+    /// Optimizer to use instead of the aircraft's usual one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimizer: Option<Optimizer>
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Pin {
+    /// Vertices where the outline turns through at least this many degrees are kept
+    #[serde(default = "default_pin_angle")]
+    pub angle: f64,
+    /// Keep the left-, right-, top- and bottom-most points
+    #[serde(default = "default_pin_extremes")]
+    pub extremes: bool
+}
+
+fn default_pin_angle() -> f64 {
+    60.0
+}
+
+fn default_pin_extremes() -> bool {
+    true
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Symmetry {
+    /// How far in feet the outline may stray from its mirror image before it's rejected
+    #[serde(default = "default_symmetry_tolerance")]
+    pub tolerance: f64
+}
+
+fn default_symmetry_tolerance() -> f64 {
+    0.5
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(untagged)]
+pub enum Rotation {
+    Degrees(f64),
+    Auto(AutoRotation)
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub enum AutoRotation {
+    #[serde(rename = "auto")]
+    Auto
 }
 
 #[derive(Deserialize, Serialize, Clone)]
 #[serde(tag = "t")]
-pub enum Optimizer {
-    #[serde(rename = "ad_floor")]
-    ADFloor {
-        a_floor: f64,
-        d_floor: f64
+pub enum Origin {
+    /// Centre of the SVG canvas
+    #[serde(rename = "canvas")]
+    Canvas,
+    /// Centre of the path's bounding box
+    #[serde(rename = "bbox")]
+    BoundingBox,
+    /// Geometric centroid of the outline
+    #[serde(rename = "centroid")]
+    Centroid,
+    /// Foremost point of the outline, horizontally centred on the bounding box
+    #[serde(rename = "nose")]
+    Nose,
+    /// Explicit SVG user-space coordinate
+    #[serde(rename = "point")]
+    Point {
+        x: f64,
+        y: f64
     },
-    #[serde(rename = "3pt_avg")]
-    ThreePointAverage {
-        dt: f64
+    /// Centre of the element with the given id, e.g. a small circle marking the main gear
+    #[serde(rename = "marker")]
+    Marker {
+        id: String
     }
 }
 
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(tag = "t")]
+pub enum Scale {
+    /// Stretch the whole SVG canvas to the configured dimensions
+    #[default]
+    #[serde(rename = "canvas")]
+    Canvas,
+    /// Stretch the path's bounding box to the configured dimensions
+    #[serde(rename = "bbox")]
+    BoundingBox,
+    /// Scale both axes by the factor that fits `axis` to its configured dimension,
+    /// failing if the other axis is more than `tolerance` (a fraction) off
+    #[serde(rename = "uniform")]
+    Uniform {
+        axis: Axis,
+        #[serde(default = "default_tolerance")]
+        tolerance: f64
+    }
+}
+
+impl Scale {
+    #[must_use]
+    pub fn is_canvas(&self) -> bool {
+        matches!(self, Scale::Canvas)
+    }
+}
+
+fn default_tolerance() -> f64 {
+    0.05
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Axis {
+    W,
+    L
+}
+
+/// Which optimizer simplifies the outline. `t` is its name in the registry (see
+/// [`crate::path::optimizer`]), everything else in the table is a parameter, e.g.
+/// `{ t = "ad_floor", a_floor = 0.15, d_floor = 0.2 }`
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Optimizer {
+    pub t: String,
+    #[serde(flatten)]
+    pub params: BTreeMap<String, f64>
+}
+
+/// Settings shared by every aircraft, the `[configuration]` table
 #[derive(Deserialize, Serialize, Clone)]
 pub struct ProgramConfigInner {
+    /// Where JSON files are written
     pub output_directory: PathBuf,
-    pub max_points: usize
-}
\ No newline at end of file
+    /// Outlines with more points than this after optimizing are an error, unless an aircraft sets its own
+    pub max_points: usize,
+    /// Winding order of the output polygons
+    #[serde(default)]
+    pub winding: Winding,
+    /// Repeat the first point at the end of each polygon
+    #[serde(default = "default_close")]
+    pub close: bool,
+    /// What to do when the optimized outline crosses itself
+    #[serde(default)]
+    pub intersections: Intersections,
+    /// Points closer than this many feet to the previous point are dropped before optimizing
+    #[serde(default = "default_epsilon")]
+    pub epsilon: f64
+}
+
+fn default_epsilon() -> f64 {
+    0.001
+}
+
+fn default_close() -> bool {
+    true
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Intersections {
+    /// Write the outline anyway
+    #[default]
+    Ignore,
+    /// Fail the aircraft, reporting the crossing segments
+    Fail,
+    /// Put back points removed by the optimizer until the outline no longer crosses itself
+    Repair
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Winding {
+    #[default]
+    Cw,
+    Ccw
+}
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use super::ProgramConfig;
+
+    const CONFIG: &str = r#"
+        [configuration]
+        output_directory = "build"
+        max_points = 100
+
+        [defaults]
+        attr = "VATSIM-Radar"
+        optimizer = { t = "ad_floor", a_floor = 0.1, d_floor = 0.1 }
+
+        [aircraft]
+        B738 = { f = "b738.svg", w = "34.3 m", max_points = 80 }
+        B38M = { extends = "B738", w = "35.9 m" }
+        B39M = { extends = "B38M", attr = "Someone", optimizer = { t = "3pt_avg", dt = 0.2 } }
+    "#;
+
+    #[test]
+    fn aircraft_inherit_defaults_and_extends() {
+        let config = ProgramConfig::parse(CONFIG, Path::new("config.toml")).expect("valid configuration");
+
+        let base = &config.aircraft["B738"];
+        assert_eq!(base.attr, "VATSIM-Radar");
+        assert_eq!(base.optimizer.t, "ad_floor");
+
+        let max = &config.aircraft["B38M"];
+        assert_eq!(max.f, base.f);
+        assert_eq!(max.max_points, Some(80));
+        assert!(max.w.is_some_and(|w| w.feet() > base.w.expect("B738 wingspan").feet()));
+
+        let other = &config.aircraft["B39M"];
+        assert_eq!(other.attr, "Someone");
+        assert_eq!(other.optimizer.t, "3pt_avg");
+        assert!(!other.optimizer.params.contains_key("a_floor"), "optimizer tables are replaced whole");
+    }
+
+    #[test]
+    fn included_aircraft_are_merged() {
+        let dir = std::env::temp_dir().join(format!("vn_svgp_include_{}", std::process::id()));
+        fs::create_dir_all(dir.join("aircraft")).expect("temp dir");
+        fs::write(dir.join("aircraft/boeing.toml"), r#"
+            [aircraft]
+            B748 = { extends = "B738", w = "68.4 m" }
+        "#).expect("write include");
+        fs::write(dir.join("aircraft/dup.toml"), r#"
+            [aircraft]
+            B38M = { f = "b38m.svg" }
+        "#).expect("write include");
+
+        let main = format!("include = [\"aircraft/boeing.toml\"]\n{CONFIG}");
+        let config = ProgramConfig::parse(&main, &dir.join("config.toml")).expect("valid configuration");
+        assert_eq!(config.aircraft["B748"].f, config.aircraft["B738"].f);
+        assert_eq!(config.files, vec![dir.join("config.toml"), dir.join("aircraft/boeing.toml")]);
+
+        let main = format!("include = [\"aircraft/*.toml\"]\n{CONFIG}");
+        let dup = ProgramConfig::parse(&main, &dir.join("config.toml")).err().expect("B38M is defined twice");
+        let message = dup.to_string();
+        assert!(message.contains("config.toml") && message.contains("dup.toml"), "{message}");
+
+        fs::remove_dir_all(&dir).expect("remove temp dir");
+    }
+
+    #[test]
+    fn profiles_replace_budget_and_optimizer() {
+        let profiles = format!("{CONFIG}{}", r#"
+            B772 = { f = "b772.svg", profiles = { low = { max_points = 60 } } }
+
+            [profiles.low]
+            max_points = 40
+            optimizer = { t = "ad_floor", a_floor = 0.5, d_floor = 0.5 }
+        "#);
+        let config = ProgramConfig::parse(&profiles, Path::new("config.toml")).expect("valid configuration");
+        let low = config.profile("low").expect("low profile");
+
+        assert_eq!(low.configuration.output_directory, Path::new("build/low"));
+        assert_eq!(low.aircraft["B738"].max_points, Some(40));
+        assert!((low.aircraft["B738"].optimizer.params["a_floor"] - 0.5).abs() < f64::EPSILON);
+        assert_eq!(low.aircraft["B772"].max_points, Some(60));
+        assert_eq!(config.aircraft["B738"].max_points, Some(80));
+        assert!(config.profile("high").is_err());
+
+        let unknown = ProgramConfig::parse(&profiles.replace("low = {", "lo = {"), Path::new("config.toml")).err().expect("lo isn't a profile");
+        assert!(unknown.to_string().contains("`lo`"), "{unknown}");
+    }
+
+    #[test]
+    fn bad_extends_are_rejected() {
+        let unknown = ProgramConfig::parse(&CONFIG.replace(r#"extends = "B738""#, r#"extends = "B737""#), Path::new("config.toml")).err().expect("B737 isn't configured");
+        assert!(unknown.to_string().contains("B737"), "{unknown}");
+
+        let looped = ProgramConfig::parse(&CONFIG.replace(r#"f = "b738.svg""#, r#"extends = "B39M""#), Path::new("config.toml")).err().expect("extends loop");
+        assert!(looped.to_string().contains("loop"), "{looped}");
+    }
+}
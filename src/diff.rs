@@ -0,0 +1,188 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::ErrorKind as IoErrorKind;
+use std::path::Path;
+use anyhow::Context;
+use serde::Serialize;
+use crate::config::{AircraftConfig, AircraftPointFile, ProgramConfig};
+use crate::error::{Error, ErrorKind};
+use crate::path;
+use crate::path::ring::distance;
+use crate::point::P;
+
+/// How a freshly built aircraft compares with what's already in the output directory
+#[derive(Serialize)]
+pub struct Entry {
+    pub aircraft: String,
+    /// Output profile, if this isn't the main output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    #[serde(flatten)]
+    pub change: Change
+}
+
+#[derive(Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    /// There's no output for it yet
+    Added { points: usize },
+    /// There's output for it, but it's no longer configured
+    Removed,
+    Changed {
+        old_points: usize,
+        new_points: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        old_attribution: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        new_attribution: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        old_aircraft_types: Option<Vec<String>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        new_aircraft_types: Option<Vec<String>>,
+        /// Furthest any vertex of either outline is from the other outline, in feet
+        max_displacement: f64
+    },
+    /// The output would be byte for byte the same
+    Unchanged
+}
+
+/// Compare a point file with the one already written for the aircraft.
+///
+/// # Errors
+/// If the existing output can't be read or parsed.
+pub fn compare(ac_typ: &str, config: &AircraftConfig, out: &Path, pf: &AircraftPointFile) -> anyhow::Result<Change> {
+    let p = out.join(format!("{ac_typ}.json"));
+    let existing = match fs::read_to_string(&p) {
+        Ok(s) => s,
+        Err(e) if e.kind() == IoErrorKind::NotFound => return Ok(Change::Added { points: pf.points.len() }),
+        Err(e) => return Err(e).with_context(|| Error::aircraft(ErrorKind::Io, ac_typ, config, format!("failed to read existing output {}", p.display())))
+    };
+    if existing == path::serialize(ac_typ, config, pf)? {
+        return Ok(Change::Unchanged);
+    }
+
+    let old: AircraftPointFile = serde_json::from_str(&existing)
+        .with_context(|| Error::aircraft(ErrorKind::Io, ac_typ, config, format!("failed to parse existing output {}", p.display())))?;
+    let attribution = (old.attribution != pf.attribution).then_some((old.attribution, pf.attribution.clone()));
+    let types = (old.aircraft_types != pf.aircraft_types).then_some((old.aircraft_types, pf.aircraft_types.clone()));
+
+    Ok(Change::Changed {
+        old_points: old.points.len(),
+        new_points: pf.points.len(),
+        max_displacement: displacement(&old.points, &pf.points),
+        old_attribution: attribution.as_ref().map(|a| a.0.clone()),
+        new_attribution: attribution.map(|a| a.1),
+        old_aircraft_types: types.as_ref().map(|t| t.0.clone()),
+        new_aircraft_types: types.map(|t| t.1)
+    })
+}
+
+/// Aircraft with output in `out` that aren't in the configuration, sorted. Only JSON files that parse as
+/// point files count as output, so build reports and hashes kept alongside aren't taken for aircraft. A
+/// missing output directory has nothing to remove.
+///
+/// # Errors
+/// If the output directory can't be listed or a JSON file in it can't be read.
+pub fn removed(out: &Path, config: &ProgramConfig, profile: Option<&str>) -> anyhow::Result<Vec<Entry>> {
+    let entries = match fs::read_dir(out) {
+        Ok(e) => e,
+        Err(e) if e.kind() == IoErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e).with_context(|| Error::new(ErrorKind::Io, format!("failed to list output directory {}", out.display())))
+    };
+
+    let mut removed = vec![];
+    for e in entries {
+        let p = e.with_context(|| Error::new(ErrorKind::Io, format!("failed to list output directory {}", out.display())))?.path();
+        let Some(id) = p.file_stem().and_then(|s| s.to_str()) else { continue };
+        if p.extension().is_none_or(|e| e != "json") || config.aircraft.contains_key(id) {
+            continue;
+        }
+        let s = fs::read_to_string(&p)
+            .with_context(|| Error::new(ErrorKind::Io, format!("failed to read {}", p.display())))?;
+        if serde_json::from_str::<AircraftPointFile>(&s).is_ok() {
+            removed.push(Entry { aircraft: id.to_string(), profile: profile.map(str::to_string), change: Change::Removed });
+        }
+    }
+    removed.sort_by(|a, b| a.aircraft.cmp(&b.aircraft));
+    Ok(removed)
+}
+
+/// Symmetric Hausdorff distance between the vertices of two rings
+fn displacement(a: &[P], b: &[P]) -> f64 {
+    let one_way = |from: &[P], to: &[P]| from.iter().map(|p| distance(p, to)).fold(0.0, f64::max);
+    if a.is_empty() || b.is_empty() {
+        return f64::INFINITY;
+    }
+    one_way(a, b).max(one_way(b, a))
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match &self.profile {
+            Some(p) => format!("{p}/{}", self.aircraft),
+            None => self.aircraft.clone()
+        };
+        match &self.change {
+            Change::Added { points } => write!(f, "+ {name} ({points} points)"),
+            Change::Removed => write!(f, "- {name}"),
+            Change::Unchanged => write!(f, "= {name}"),
+            Change::Changed { old_points, new_points, old_attribution, new_attribution, old_aircraft_types, new_aircraft_types, max_displacement } => {
+                write!(f, "~ {name}: ")?;
+                if old_points == new_points {
+                    write!(f, "{new_points} points")?;
+                } else {
+                    write!(f, "{old_points} -> {new_points} points")?;
+                }
+                write!(f, ", max displacement {max_displacement:.3} ft")?;
+                if let (Some(old), Some(new)) = (old_attribution, new_attribution) {
+                    write!(f, ", attribution {old:?} -> {new:?}")?;
+                }
+                if let (Some(old), Some(new)) = (old_aircraft_types, new_aircraft_types) {
+                    write!(f, ", aircraft types {} -> {}", old.join(","), new.join(","))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::config::ProgramConfig;
+    use crate::point::P;
+    use super::{displacement, removed};
+
+    #[test]
+    fn displacement_is_symmetric() {
+        let square = [P::from((0.0, 0.0)), P::from((2.0, 0.0)), P::from((2.0, 2.0)), P::from((0.0, 2.0))];
+        let notched = [P::from((0.0, 0.0)), P::from((2.0, 0.0)), P::from((2.0, 2.0)), P::from((1.0, 1.5)), P::from((0.0, 2.0))];
+        assert!(displacement(&square, &square).abs() < 1e-12);
+        assert!((displacement(&square, &notched) - 0.5).abs() < 1e-12);
+        assert!((displacement(&notched, &square) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn only_point_files_are_removed() {
+        let dir = std::env::temp_dir().join(format!("vn_svgp_removed_{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("create output directory");
+        fs::write(dir.join("B738.json"), r#"{"points":[],"aircraftTypes":["B738"],"attribution":"x"}"#).expect("write output");
+        fs::write(dir.join("A320.json"), r#"{"points":[],"aircraftTypes":["A320"],"attribution":"x"}"#).expect("write output");
+        fs::write(dir.join("report.json"), r#"{"ok":1,"failed":0,"aircraft":[]}"#).expect("write report");
+        fs::write(dir.join("hashes.json"), r#"{"build/B738":"0123456789abcdef"}"#).expect("write hashes");
+
+        let config: ProgramConfig = toml_edit::de::from_str(r#"
+            [configuration]
+            output_directory = "build"
+            max_points = 100
+
+            [aircraft]
+            A320 = { f = "a320.svg", attr = "x", optimizer = { t = "ad_floor", a_floor = 0.1, d_floor = 0 } }
+        "#).expect("valid configuration");
+        let removed = removed(&dir, &config, None);
+        fs::remove_dir_all(&dir).expect("remove output directory");
+
+        let ids = removed.expect("removed").into_iter().map(|e| e.aircraft).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["B738"]);
+    }
+}
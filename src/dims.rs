@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use crate::config::AircraftConfig;
+use crate::error::{Error, ErrorKind};
+
+pub const FEET_PER_METRE: f64 = 3.280_84;
+
+/// Embedded table of common ICAO types, wingspan and length in metres
+const BUILTIN: &str = include_str!("dims.csv");
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Unit {
+    Metres,
+    Feet
+}
+
+impl Unit {
+    #[must_use]
+    pub fn to_feet(self, v: f64) -> f64 {
+        match self {
+            Unit::Metres => v * FEET_PER_METRE,
+            Unit::Feet => v
+        }
+    }
+}
+
+impl FromStr for Unit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "m" => Ok(Unit::Metres),
+            "ft" => Ok(Unit::Feet),
+            _ => bail!("unknown unit `{s}`, expected `m` or `ft`")
+        }
+    }
+}
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unit::Metres => write!(f, "m"),
+            Unit::Feet => write!(f, "ft")
+        }
+    }
+}
+
+/// A length along with the unit it was written in.
+///
+/// In configuration this is either a bare number of feet, a string such as `"35.1 m"` or `"115 ft"`,
+/// or a table with a single unit key such as `{ m = 35.1 }`.
+#[derive(Copy, Clone, Deserialize, Serialize)]
+#[serde(try_from = "LengthRepr", into = "LengthRepr")]
+pub struct Length {
+    pub value: f64,
+    pub unit: Unit
+}
+
+impl Length {
+    #[must_use]
+    pub fn feet(self) -> f64 {
+        self.unit.to_feet(self.value)
+    }
+}
+
+impl Display for Length {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.value, self.unit)
+    }
+}
+
+impl FromStr for Length {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        // the unit starts after the last digit, so exponents such as `1e3 m` stay with the number
+        let split = s.rfind(|c: char| c.is_ascii_digit() || c == '.').map_or(0, |i| i + 1);
+        let (value, unit) = s.split_at(split);
+        let value = value.trim().parse::<f64>()
+            .with_context(|| format!("invalid length `{s}`, expected e.g. `35.1 m` or `115 ft`"))?;
+        let unit = unit.trim();
+        let unit = if unit.is_empty() { Unit::Feet } else { unit.parse()? };
+        Ok(Length { value, unit })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum LengthRepr {
+    Feet(f64),
+    Str(String),
+    Table {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        m: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ft: Option<f64>
+    }
+}
+
+impl TryFrom<LengthRepr> for Length {
+    type Error = anyhow::Error;
+
+    fn try_from(value: LengthRepr) -> Result<Self, Self::Error> {
+        match value {
+            LengthRepr::Feet(value) | LengthRepr::Table { m: None, ft: Some(value) } => Ok(Length { value, unit: Unit::Feet }),
+            LengthRepr::Str(s) => s.parse(),
+            LengthRepr::Table { m: Some(value), ft: None } => Ok(Length { value, unit: Unit::Metres }),
+            LengthRepr::Table { .. } => bail!("length table must have exactly one of `m` or `ft`")
+        }
+    }
+}
+
+impl From<Length> for LengthRepr {
+    fn from(value: Length) -> Self {
+        match value.unit {
+            Unit::Feet => LengthRepr::Feet(value.value),
+            Unit::Metres => LengthRepr::Str(value.to_string())
+        }
+    }
+}
+
+/// Wingspan and length of a single type
+#[derive(Copy, Clone)]
+pub struct Dims {
+    pub w: Length,
+    pub l: Length
+}
+
+/// Where a resolved dimension came from
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Source {
+    Config,
+    Builtin
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Config => write!(f, "config"),
+            Source::Builtin => write!(f, "built-in table")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TomlDims {
+    w: TomlLength,
+    l: TomlLength
+}
+
+/// A length in a dimensions table, where bare numbers are in the unit given for the table
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TomlLength {
+    Bare(f64),
+    Length(Length)
+}
+
+impl TomlLength {
+    fn with_unit(self, unit: Unit) -> Length {
+        match self {
+            TomlLength::Bare(value) => Length { value, unit },
+            TomlLength::Length(l) => l
+        }
+    }
+}
+
+/// Parse a `type,wingspan,length` CSV table. Lines starting with `#` and a non-numeric header row are ignored.
+/// Bare numbers are taken to be in `unit`, but individual cells may carry their own (`35.1 m`).
+///
+/// # Errors
+/// If a row is missing columns or has a wingspan or length that isn't a length.
+pub fn parse_csv(s: &str, unit: Unit) -> anyhow::Result<BTreeMap<String, Dims>> {
+    let mut t = BTreeMap::new();
+    let mut seen_row = false;
+    for (n, line) in s.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let cols = line.split(',').map(str::trim).collect::<Vec<_>>();
+        if cols.len() < 3 {
+            bail!("line {}: expected `type,wingspan,length`", n + 1);
+        }
+        let (Ok(w), Ok(l)) = (parse_cell(cols[1], unit), parse_cell(cols[2], unit)) else {
+            if !seen_row {
+                seen_row = true;
+                continue; // header
+            }
+            bail!("line {}: wingspan and length must be lengths", n + 1);
+        };
+        seen_row = true;
+        t.insert(cols[0].to_uppercase(), Dims { w, l });
+    }
+    Ok(t)
+}
+
+fn parse_cell(s: &str, unit: Unit) -> anyhow::Result<Length> {
+    match s.parse::<f64>() {
+        Ok(value) => Ok(Length { value, unit }),
+        Err(_) => s.parse()
+    }
+}
+
+/// Read a dimensions table keyed by ICAO type designator.
+///
+/// `.toml` files are tables of `TYPE = { w = .., l = .. }` using the same length syntax as the configuration,
+/// anything else is read as CSV. Either way, bare numbers are taken to be in `unit`.
+///
+/// # Errors
+/// If the file can't be read or parsed.
+pub fn read(file: &Path, unit: Unit) -> anyhow::Result<BTreeMap<String, Dims>> {
+    let s = fs::read_to_string(file)
+        .with_context(|| Error::new(ErrorKind::Io, format!("failed to read dimensions table {}", file.display())))?;
+
+    if file.extension().is_some_and(|e| e.eq_ignore_ascii_case("toml")) {
+        let t: BTreeMap<String, TomlDims> = toml_edit::de::from_str(&s)
+            .with_context(|| Error::new(ErrorKind::Config, format!("failed to parse dimensions table {}", file.display())))?;
+        Ok(t.into_iter()
+            .map(|(k, v)| (k.to_uppercase(), Dims { w: v.w.with_unit(unit), l: v.l.with_unit(unit) }))
+            .collect())
+    } else {
+        parse_csv(&s, unit).with_context(|| Error::new(ErrorKind::Config, format!("failed to parse dimensions table {}", file.display())))
+    }
+}
+
+/// Embedded table of common ICAO types
+///
+/// # Panics
+/// If the embedded table is invalid, which would be a bug in this crate.
+pub fn builtin() -> &'static BTreeMap<String, Dims> {
+    static TABLE: OnceLock<BTreeMap<String, Dims>> = OnceLock::new();
+    TABLE.get_or_init(|| parse_csv(BUILTIN, Unit::Metres).expect("built-in dimensions table is invalid"))
+}
+
+/// Dimensions of a type from the built-in table
+#[must_use]
+pub fn lookup(ac_typ: &str) -> Option<Dims> {
+    builtin().get(&ac_typ.to_uppercase()).copied()
+}
+
+/// Dimensions of a configured aircraft in feet, along with where each value came from
+pub struct Resolved {
+    pub w: f64,
+    pub l: f64,
+    pub w_source: Source,
+    pub l_source: Source
+}
+
+/// Resolve the wingspan and length of an aircraft. Values set in the configuration always win,
+/// anything left out is taken from the built-in table.
+///
+/// # Errors
+/// If a dimension isn't configured and the type isn't in the built-in table either.
+pub fn resolve(ac_typ: &str, config: &AircraftConfig) -> anyhow::Result<Resolved> {
+    let table = lookup(ac_typ);
+    let (w, w_source) = match (config.w, table) {
+        (Some(w), _) => (w.feet(), Source::Config),
+        (None, Some(d)) => (d.w.feet(), Source::Builtin),
+        (None, None) => bail!(Error::aircraft(ErrorKind::Config, ac_typ, config, format!("no wingspan configured and {ac_typ} is not in the built-in dimensions table, please set `w`")))
+    };
+    let (l, l_source) = match (config.l, table) {
+        (Some(l), _) => (l.feet(), Source::Config),
+        (None, Some(d)) => (d.l.feet(), Source::Builtin),
+        (None, None) => bail!(Error::aircraft(ErrorKind::Config, ac_typ, config, format!("no length configured and {ac_typ} is not in the built-in dimensions table, please set `l`")))
+    };
+    Ok(Resolved { w, l, w_source, l_source })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::config::AircraftConfig;
+    use crate::error::{Error, ErrorKind};
+    use super::{read, resolve, Length, Source, Unit, FEET_PER_METRE};
+
+    #[test]
+    fn lengths_parse_with_exponents() {
+        let l: Length = "1e3 m".parse().expect("length");
+        assert!((l.value - 1000.0).abs() < 1e-9 && l.unit == Unit::Metres);
+        let l: Length = "115ft".parse().expect("length");
+        assert!((l.value - 115.0).abs() < 1e-9 && l.unit == Unit::Feet);
+        assert!("m".parse::<Length>().is_err());
+    }
+
+    #[test]
+    fn toml_tables_use_the_unit_for_bare_numbers() {
+        let file = std::env::temp_dir().join(format!("vn_svgp_dims_{}.toml", std::process::id()));
+        fs::write(&file, "B738 = { w = 35.8, l = \"129.5 ft\" }\n").expect("write table");
+        let t = read(&file, Unit::Metres);
+        fs::remove_file(&file).expect("remove table");
+        let d = t.expect("table")["B738"];
+        assert!(d.w.unit == Unit::Metres && d.l.unit == Unit::Feet);
+    }
+
+    #[test]
+    fn omitted_dimensions_come_from_the_builtin_table() {
+        let config: AircraftConfig = toml_edit::de::from_str(r#"
+            f = "b738.svg"
+            attr = ""
+            w = 100
+            optimizer = { t = "3pt_avg", dt = 0.0 }
+        "#).expect("valid config");
+
+        let d = resolve("B738", &config).expect("dimensions");
+        assert!((d.w - 100.0).abs() < 1e-9 && d.w_source == Source::Config);
+        assert!((d.l - 39.2 * FEET_PER_METRE).abs() < 1e-9 && d.l_source == Source::Builtin);
+
+        let e = resolve("ZZZZ", &config).err().expect("ZZZZ is not in the table");
+        assert_eq!(e.downcast_ref::<Error>().map(|e| e.kind), Some(ErrorKind::Config));
+    }
+}
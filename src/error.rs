@@ -0,0 +1,151 @@
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use serde::Serialize;
+use crate::config::AircraftConfig;
+
+/// What went wrong, each with its own exit code
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Anything not covered below
+    Other,
+    /// The configuration is invalid or doesn't fit the art
+    Config,
+    /// A file couldn't be read or written
+    Io,
+    /// An SVG couldn't be parsed
+    SvgParse,
+    /// There's no usable path in the SVG
+    NoPath,
+    /// The path has no area, can't be transformed or can't be made into a clean outline
+    DegeneratePath,
+    /// The outline is still over `max_points` after optimizing
+    TooManyPoints
+}
+
+impl ErrorKind {
+    /// Process exit code. 2 is left to command line usage errors.
+    #[must_use]
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Config => 3,
+            ErrorKind::Io => 4,
+            ErrorKind::SvgParse => 5,
+            ErrorKind::NoPath => 6,
+            ErrorKind::DegeneratePath => 7,
+            ErrorKind::TooManyPoints => 8
+        }
+    }
+
+    /// Kind of the outermost [`Error`] in the chain, or [`ErrorKind::Other`] if there's none
+    #[must_use]
+    pub fn of(e: &anyhow::Error) -> Self {
+        e.downcast_ref::<Error>().map_or(ErrorKind::Other, |e| e.kind)
+    }
+}
+
+/// An error with a kind, and the aircraft and file it's about if there is one.
+///
+/// These travel inside `anyhow::Error`, usually as context, and are found again with `downcast_ref`.
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub aircraft: Option<String>,
+    pub file: Option<PathBuf>,
+    pub message: String
+}
+
+impl Error {
+    #[must_use]
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Error { kind, aircraft: None, file: None, message: message.into() }
+    }
+
+    /// An error about a configured aircraft
+    #[must_use]
+    pub fn aircraft(kind: ErrorKind, ac_typ: &str, config: &AircraftConfig, message: impl Into<String>) -> Self {
+        Error { kind, aircraft: Some(ac_typ.to_string()), file: Some(config.f.clone()), message: message.into() }
+    }
+
+    /// The `[TYPE:file] ` put in front of human readable messages
+    fn prefix(&self) -> String {
+        match (&self.aircraft, &self.file) {
+            (Some(a), Some(p)) => format!("[{}:{}] ", a, p.display()),
+            (Some(a), None) => format!("[{a}] "),
+            _ => String::new()
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.prefix(), self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A failure as one JSON object, for `--message-format json`
+#[derive(Serialize)]
+pub struct Diagnostic {
+    pub kind: ErrorKind,
+    pub code: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aircraft: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// The whole chain of messages, outermost first, without `[TYPE:file]` prefixes
+    pub message: String
+}
+
+impl From<&anyhow::Error> for Diagnostic {
+    fn from(e: &anyhow::Error) -> Self {
+        let typed = e.downcast_ref::<Error>();
+        let kind = ErrorKind::of(e);
+        let prefix = typed.map(Error::prefix).unwrap_or_default();
+
+        // context added with `format!` carries the same prefix, so strip it from every layer
+        let message = e.chain()
+            .map(|c| {
+                let s = c.to_string();
+                s.strip_prefix(prefix.as_str()).map_or(s.clone(), str::to_string)
+            })
+            .collect::<Vec<_>>()
+            .join(": ");
+
+        Diagnostic {
+            kind,
+            code: kind.exit_code(),
+            aircraft: typed.and_then(|t| t.aircraft.clone()),
+            file: typed.and_then(|t| t.file.clone()),
+            message
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+    use super::{Diagnostic, Error, ErrorKind};
+
+    #[test]
+    fn kind_is_found_through_context() {
+        let e = Err::<(), _>(Error { kind: ErrorKind::NoPath, aircraft: Some("B738".to_string()), file: Some("b738.svg".into()), message: "No valid path could be found".to_string() })
+            .context("[B738:b738.svg] No path element could be found")
+            .expect_err("error");
+        assert_eq!(ErrorKind::of(&e), ErrorKind::NoPath);
+
+        let d = Diagnostic::from(&e);
+        assert_eq!(d.code, ErrorKind::NoPath.exit_code());
+        assert_eq!(d.aircraft.as_deref(), Some("B738"));
+        assert_eq!(d.message, "No path element could be found: No valid path could be found");
+    }
+
+    #[test]
+    fn untyped_errors_are_other() {
+        let e = anyhow::anyhow!("something broke");
+        assert_eq!(ErrorKind::of(&e), ErrorKind::Other);
+        assert_eq!(Diagnostic::from(&e).code, 1);
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use anyhow::Context;
+use toml_edit::{DocumentMut, InlineTable, Item, Table, Value};
+use crate::config::ProgramConfig;
+use crate::dims::{Dims, Length, Unit};
+use crate::error::{Error, ErrorKind};
+
+/// Types added by [`import`], and types skipped because no dimensions were known for them
+pub struct ImportResult {
+    pub added: Vec<String>,
+    pub no_dims: Vec<String>
+}
+
+/// Insert an `[aircraft]` entry for every SVG in `source_dir` that has dimensions but isn't configured yet,
+/// either in `doc` or in a file it includes, which `configured` holds the aircraft of.
+///
+/// The type designator is taken from the upper-cased file stem, i.e. `source/b738.svg` becomes `B738`.
+///
+/// # Errors
+/// If `source_dir` can't be read or `aircraft` in the document isn't a table.
+pub fn import(doc: &mut DocumentMut, configured: &ProgramConfig, source_dir: &Path, dims: &BTreeMap<String, Dims>, attr: &str) -> anyhow::Result<ImportResult> {
+    let mut files = vec![];
+    for entry in fs::read_dir(source_dir)
+        .with_context(|| Error::new(ErrorKind::Io, format!("failed to read source directory {}", source_dir.display())))? {
+        let p = entry.with_context(|| Error::new(ErrorKind::Io, format!("failed to read source directory {}", source_dir.display())))?.path();
+        if p.is_file() && p.extension().is_some_and(|e| e.eq_ignore_ascii_case("svg")) {
+            files.push(p);
+        }
+    }
+    files.sort();
+
+    let aircraft = doc.entry("aircraft")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_like_mut()
+        .ok_or_else(|| Error::new(ErrorKind::Config, "`aircraft` in configuration is not a table"))?;
+
+    let mut result = ImportResult { added: vec![], no_dims: vec![] };
+
+    for p in files {
+        let Some(typ) = p.file_stem().map(|s| s.to_string_lossy().to_uppercase()) else { continue };
+        if aircraft.contains_key(&typ) || configured.aircraft.contains_key(&typ) {
+            continue;
+        }
+        let Some(d) = dims.get(&typ) else {
+            result.no_dims.push(typ);
+            continue;
+        };
+
+        let mut optimizer = InlineTable::new();
+        optimizer.insert("t", "ad_floor".into());
+        optimizer.insert("a_floor", 0.15.into());
+        optimizer.insert("d_floor", 0.2.into());
+
+        let mut entry = InlineTable::new();
+        entry.insert("f", p.to_string_lossy().as_ref().into());
+        entry.insert("attr", attr.into());
+        entry.insert("w", length_value(d.w));
+        entry.insert("l", length_value(d.l));
+        entry.insert("optimizer", Value::InlineTable(optimizer));
+
+        aircraft.insert(&typ, Item::Value(Value::InlineTable(entry)));
+        result.added.push(typ);
+    }
+
+    Ok(result)
+}
+
+fn length_value(l: Length) -> Value {
+    match l.unit {
+        Unit::Feet => l.value.into(),
+        Unit::Metres => l.to_string().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use toml_edit::DocumentMut;
+    use crate::config::ProgramConfig;
+    use crate::dims::{Dims, Length, Unit};
+    use super::import;
+
+    #[test]
+    fn skips_configured_types_and_unknown_dimensions() {
+        let dir = std::env::temp_dir().join(format!("vn_svgp_import_{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("create source directory");
+        for f in ["a320.svg", "b738.svg", "c172.svg", "zzzz.svg"] {
+            fs::write(dir.join(f), "<svg/>").expect("write svg");
+        }
+
+        let m = |value| Length { value, unit: Unit::Metres };
+        let dims = ["A320", "B738", "C172"].into_iter()
+            .map(|t| (t.to_string(), Dims { w: m(35.8), l: m(37.6) }))
+            .collect::<BTreeMap<_, _>>();
+
+        // B738 is in the document itself, C172 only in a file it includes
+        let mut doc: DocumentMut = r#"
+            [aircraft]
+            B738 = { f = "b738.svg", attr = "x", optimizer = { t = "ad_floor", a_floor = 0.1, d_floor = 0 } }
+        "#.parse().expect("valid document");
+        let configured: ProgramConfig = toml_edit::de::from_str(r#"
+            [configuration]
+            output_directory = "build"
+            max_points = 100
+
+            [aircraft]
+            B738 = { f = "b738.svg", attr = "x", optimizer = { t = "ad_floor", a_floor = 0.1, d_floor = 0 } }
+            C172 = { f = "c172.svg", attr = "x", optimizer = { t = "ad_floor", a_floor = 0.1, d_floor = 0 } }
+        "#).expect("valid configuration");
+
+        let res = import(&mut doc, &configured, &dir, &dims, "someone");
+        fs::remove_dir_all(&dir).expect("remove source directory");
+        let res = res.expect("import");
+
+        assert_eq!(res.added, vec!["A320"]);
+        assert_eq!(res.no_dims, vec!["ZZZZ"]);
+        let aircraft = doc["aircraft"].as_table().expect("aircraft table");
+        assert_eq!(aircraft.iter().map(|(k, _)| k).collect::<Vec<_>>(), vec!["B738", "A320"]);
+        assert_eq!(doc["aircraft"]["A320"]["w"].as_str(), Some("35.8 m"));
+        assert_eq!(doc["aircraft"]["A320"]["attr"].as_str(), Some("someone"));
+    }
+}
@@ -0,0 +1,36 @@
+#![deny(clippy::unwrap_used)]
+#![warn(clippy::pedantic)]
+#![warn(clippy::cargo)]
+
+//! Turn aircraft silhouette SVGs into vNAS Tower View JSON files.
+//!
+//! The pipeline is the same one the `vn_svgp` binary runs: load a [`config::ProgramConfig`], read an SVG
+//! with [`path::read`] (or [`path::parse`] for bytes already in memory), turn it into points with
+//! [`path::pathificate`] and write it out with [`path::write`] or [`path::serialize`].
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use vn_svgp::{config::ProgramConfig, path};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let config = ProgramConfig::load(Path::new("config.toml"))?;
+//! let b738 = &config.aircraft["B738"];
+//! let tree = path::read("B738", b738)?;
+//! let pf = path::pathificate("B738", b738, &config.configuration, &tree)?;
+//! println!("{}", path::serialize("B738", b738, &pf)?);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod config;
+mod bezier;
+pub mod point;
+pub mod path;
+pub mod status;
+pub mod import;
+pub mod dims;
+pub mod error;
+pub mod report;
+pub mod select;
+pub mod diff;
+pub mod watch;
@@ -2,20 +2,25 @@
 #![warn(clippy::pedantic)]
 #![warn(clippy::cargo)]
 
-mod config;
-mod bezier;
-mod point;
-mod path;
-
+use std::collections::HashMap;
 use std::fs;
 use std::fs::{create_dir_all};
+use std::path::{Path, PathBuf};
 use std::process::exit;
-use anyhow::{Context};
-use clap::{arg, ArgAction, ArgMatches, command, Command, value_parser};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use anyhow::{bail, Context};
+use clap::{arg, ArgAction, ArgGroup, ArgMatches, command, Command, value_parser};
 use rayon::prelude::*;
-use toml_edit::{DocumentMut, Table, TableLike};
-use toml_edit::visit_mut::VisitMut;
-use crate::config::{AircraftConfig, Optimizer, ProgramConfig};
+use toml_edit::{DocumentMut, InlineTable};
+use vn_svgp::{diff, dims, import, path, status};
+use vn_svgp::config::{AircraftConfig, ProgramConfig};
+use vn_svgp::error::{Diagnostic, Error, ErrorKind};
+use vn_svgp::path::optimizer;
+use vn_svgp::path::stats::Stats;
+use vn_svgp::report::{Entry, Report, Status};
+use vn_svgp::select::{Hashes, Selection};
+use vn_svgp::watch::{self, Outcome, Watcher};
 
 #[allow(clippy::too_many_lines)]
 fn main() {
@@ -24,118 +29,203 @@ fn main() {
             arg!(-c --config <FILE> "Configuration file")
                 .required(true)
         )
+        .arg(
+            arg!(--"message-format" <FORMAT> "How failures are reported, one JSON object per failure on stdout with `json`")
+                .value_parser(["human", "json"])
+                .default_value("human")
+                .global(true)
+        )
         .subcommand(
             Command::new("build")
                 .about("Build all aircraft JSON according to the configuration file")
                 .arg(arg!(-k --keepgoing "Ignore failures").action(ArgAction::SetTrue))
+                .arg(arg!(-a --aircraft <AID> "Only build these aircraft IDs, which may be globs such as `B7*`. May be repeated").action(ArgAction::Append))
+                .arg(arg!(-r --regex <REGEX> "Only build aircraft whose ID matches this regular expression. May be repeated").action(ArgAction::Append).value_parser(|s: &str| regex::Regex::new(s)))
+                .arg(arg!(-t --tag <TAG> "Only build aircraft with this tag. May be repeated").action(ArgAction::Append))
+                .arg(arg!(--since <FILE> "Only build aircraft whose SVG or configuration changed since the hashes in FILE, then record the new ones there").value_parser(value_parser!(PathBuf)))
+                .arg(arg!(-P --profile <NAME> "Only build this output profile, skipping the main output. May be repeated").action(ArgAction::Append))
+                .arg(arg!(-n --"dry-run" "Build everything but write no output").action(ArgAction::SetTrue))
+                .arg(arg!(--diff "Compare the new output with what's in the output directory: added and removed aircraft, point counts, attribution and how far vertices moved").action(ArgAction::SetTrue))
+                .arg(arg!(--report <FILE> "Write a JSON summary of every aircraft: status, errors, point counts and timings of each stage, and fidelity").value_parser(value_parser!(PathBuf)))
         )
         .subcommand(
-            Command::new("minmax")
-                .about("Minmax an aircraft and find the lowest a-floor and d-floor values possible")
+            tuning_args(Command::new("minmax"))
+                .about("Minmax an aircraft and find the lowest optimizer parameters possible")
                 .arg(arg!(-a --aircraft <AID> "Single AID to minmax").required(true))
-                .arg(arg!(-f --afloor <AFLOOR> "Maximum A-floor value").required(true).value_parser(value_parser!(f64)))
-                .arg(arg!(-s --astep <ASTEP> "A-step").required(true).value_parser(value_parser!(f64)))
-                .arg(arg!(-d --dfloor <DFLOOR> "Maximum D-floor value").required(true).value_parser(value_parser!(f64)))
-                .arg(arg!(-S --dstep <DSTEP> "D-step").required(true).value_parser(value_parser!(f64)))
         )
         .subcommand(
-            Command::new("minmax_all")
-                .about("Minmax all aircraft and find the lowest a-floor and d-floor values possible")
-                .arg(arg!(-f --afloor <AFLOOR> "Maximum A-floor value").required(true).value_parser(value_parser!(f64)))
-                .arg(arg!(-s --astep <ASTEP> "A-step").required(true).value_parser(value_parser!(f64)))
-                .arg(arg!(-d --dfloor <DFLOOR> "Maximum D-floor value").required(true).value_parser(value_parser!(f64)))
-                .arg(arg!(-S --dstep <DSTEP> "D-step").required(true).value_parser(value_parser!(f64)))
+            tuning_args(Command::new("minmax_all"))
+                .about("Minmax all aircraft and find the lowest optimizer parameters possible")
         )
         .subcommand(
             Command::new("build_one")
                 .arg(arg!(-a --aircraft <AID> "Single AID to build").required(true))
                 .arg(arg!(-d --debug "Output CSV to stdout in addition to a json").action(ArgAction::SetTrue))
         )
+        .subcommand(
+            Command::new("watch")
+                .about("Rebuild aircraft whenever their SVG or the configuration changes")
+                .arg(arg!(-p --preview <DIR> "Directory to render an SVG preview of each built aircraft into").default_value("preview").value_parser(value_parser!(PathBuf)))
+                .arg(arg!(-i --interval <MS> "How often to check for changes, in milliseconds").default_value("500").value_parser(value_parser!(u64)))
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Cross-reference the configuration against a source directory of SVGs")
+                .arg(arg!(-s --source <DIR> "Source directory to check").default_value("source").value_parser(value_parser!(PathBuf)))
+        )
+        .subcommand(
+            Command::new("import-dims")
+                .about("Add configuration entries for unconfigured SVGs using a local table of aircraft dimensions")
+                .arg(arg!(-t --table <FILE> "CSV (type,wingspan,length) or TOML table of dimensions, defaults to the built-in table").value_parser(value_parser!(PathBuf)))
+                .arg(arg!(-u --units <UNITS> "Units of bare numbers in the dimensions table (m or ft)").default_value("m").value_parser(|s: &str| s.parse::<dims::Unit>()))
+                .arg(arg!(-s --source <DIR> "Source directory to scan for SVGs").default_value("source").value_parser(value_parser!(PathBuf)))
+                .arg(arg!(--attr <ATTR> "Attribution for new entries").default_value("VATSIM-Radar"))
+                .arg(arg!(-n --"dry-run" "Print the resulting configuration instead of writing it").action(ArgAction::SetTrue))
+        )
+        .subcommand(
+            Command::new("dims")
+                .about("Print the wingspan and length used for each aircraft and where they came from")
+                .arg(arg!(-a --aircraft <AID> "Single AID to print"))
+        )
         .subcommand_required(true)
         .get_matches();
 
+    let format = match matches.get_one::<String>("message-format").map(String::as_str) {
+        Some("json") => MessageFormat::Json,
+        _ => MessageFormat::Human
+    };
+
     let config_path: &String = if let Some(f) = matches.get_one("config") { f } else {
         eprintln!("config path is required");
-        exit(1);
+        exit(2);
     };
 
-    let config_s = match fs::read_to_string(config_path) {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("error reading configuration at {config_path}: {e}");
-            exit(1);
-        }
-    };
-    let mut config: ProgramConfig = match toml_edit::de::from_str(&config_s) {
+    let mut config = match ProgramConfig::load(Path::new(config_path)) {
         Ok(c) => c,
-        Err(e) => {
-            eprintln!("error parsing configuration at {config_path}: {e}");
-            exit(1);
-        }
+        Err(e) => fail(&e, format)
     };
 
-    // create output dir
-    match create_dir_all(&config.configuration.output_directory).context("Failed to create output directory") {
-        Ok(()) => (),
-        Err(e) => {
-            eprintln!("{e:#}");
-            exit(1);
-        }
-    }
-
     match matches.subcommand() {
         Some(("build", m)) => {
-            let mut failures = 0;
-            for (typ, cfg) in config.aircraft {
-                let t = match path::read(&typ, &cfg) {
-                    Ok(r) => r,
-                    Err(e) => {
-                        failures += 1;
-                        eprintln!("{e:#}");
-                        continue;
+            let selection = Selection {
+                ids: m.get_many::<String>("aircraft").into_iter().flatten().cloned().collect(),
+                regexes: m.get_many::<regex::Regex>("regex").into_iter().flatten().cloned().collect(),
+                tags: m.get_many::<String>("tag").into_iter().flatten().cloned().collect()
+            };
+            let ids = selection.apply(&config).unwrap_or_else(|e| fail(&e, format));
+            let targets = targets(&config, m.get_many::<String>("profile").map(|p| p.cloned().collect()))
+                .unwrap_or_else(|e| fail(&e, format));
+            let since = m.get_one::<PathBuf>("since");
+            let mut hashes = since.map(|f| Hashes::load(f).unwrap_or_else(|e| fail(&e, format))).unwrap_or_default();
+            let dry_run = m.get_flag("dry-run");
+            let mut changes = m.get_flag("diff").then(Vec::new);
+
+            let mut failures = vec![];
+            let mut entries = vec![];
+            for (profile, config) in &targets {
+                let out = config.configuration.output_directory.as_path();
+                let human = matches!(format, MessageFormat::Human);
+                if let (Some(p), true) = (profile, human) {
+                    eprintln!("building profile `{p}` into {}", out.display());
+                }
+                let mut ids = ids.clone();
+                if since.is_some() {
+                    ids.retain(|id| hashes.changed(id, config));
+                    if human {
+                        eprintln!("{} aircraft changed since the last build", ids.len());
                     }
-                };
-                let pf = match path::pathificate(&typ, &cfg, config.configuration.max_points, &t) {
-                    Ok(r) => r,
-                    Err(e) => {
-                        failures += 1;
-                        eprintln!("{e:#}");
-                        continue;
+                }
+                if !dry_run {
+                    if let Err(e) = create_dir_all(out).with_context(|| Error::new(ErrorKind::Io, "Failed to create output directory")) {
+                        fail(&e, format);
                     }
-                };
-                match path::write(&typ, config.configuration.output_directory.as_path(), &cfg, &pf) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        failures += 1;
-                        eprintln!("{e:#}");
-                        continue;
+                }
+
+                for typ in ids {
+                    let cfg = &config.aircraft[&typ];
+                    let start = Instant::now();
+                    let mut stats = Stats::default();
+                    let res = path::read(&typ, cfg)
+                        .and_then(|t| path::pathificate_with_stats(&typ, cfg, &config.configuration, &t, &mut stats))
+                        .and_then(|pf| {
+                            if let Some(changes) = &mut changes {
+                                changes.push(diff::Entry { aircraft: typ.clone(), profile: profile.clone(), change: diff::compare(&typ, cfg, out, &pf)? });
+                            }
+                            if !dry_run {
+                                path::write(&typ, out, cfg, &pf)?;
+                            }
+                            Ok(pf.points.len())
+                        });
+                    if human {
+                        print_notes(&typ, cfg, &stats, res.as_ref().ok().copied());
+                    }
+                    if let Err(e) = &res {
+                        report(e, format);
+                        failures.push(ErrorKind::of(e));
+                        hashes.forget(&typ, config);
+                    } else {
+                        hashes.record(&typ, config);
+                    }
+                    entries.push(Entry {
+                        status: if res.is_ok() { Status::Ok } else { Status::Failed },
+                        error: res.err().as_ref().map(Diagnostic::from),
+                        ms: start.elapsed().as_secs_f64() * 1000.0,
+                        aircraft: typ,
+                        profile: profile.clone(),
+                        file: cfg.f.clone(),
+                        stats
+                    });
+                }
+                if let Some(changes) = &mut changes {
+                    match diff::removed(out, config, profile.as_deref()) {
+                        Ok(removed) => changes.extend(removed),
+                        Err(e) => fail(&e, format)
                     }
                 }
             }
-            if failures > 0 {
-                eprintln!("{failures} aircraft could not be pathificated, please check above for details");
+            if let Some(changes) = changes {
+                print_changes(&changes, format);
+            }
+            if let (Some(file), false) = (since, dry_run) {
+                if let Err(e) = hashes.save(file) {
+                    fail(&e, format);
+                }
+            }
+            if let Some(file) = m.get_one::<PathBuf>("report") {
+                if let Err(e) = Report::new(entries).write(file) {
+                    fail(&e, format);
+                }
+            }
+            if let Some(first) = failures.first() {
+                if matches!(format, MessageFormat::Human) {
+                    eprintln!("{} aircraft could not be pathificated, please check above for details", failures.len());
+                }
                 if !m.get_flag("keepgoing") {
-                    exit(1);
+                    // only a single kind of failure gets its own exit code
+                    let code = if failures.iter().all(|k| k == first) { first.exit_code() } else { 1 };
+                    exit(code);
                 }
-            } else {
-                println!("Pathification successful :D");
+            } else if matches!(format, MessageFormat::Human) {
+                println!("Pathification successful");
             }
         },
         Some(("build_one", m)) => {
             let aid = m.get_one::<String>("aircraft").expect("aircraft ID is required");
             let is_debug = m.get_flag("debug");
 
-            let cfg = config.aircraft.get(aid).expect("Aircraft ID is not present in configuration");
+            let cfg = config.aircraft.get(aid).unwrap_or_else(|| fail(&not_configured(aid), format));
 
-            let t = match path::read(&aid, &cfg) {
+            let t = match path::read(aid, cfg) {
                 Ok(r) => r,
-                Err(e) => {
-                    eprintln!("{e:#}");
-                    exit(1);
-                }
+                Err(e) => fail(&e, format)
             };
 
-            let pf = match path::pathificate(aid, cfg, config.configuration.max_points, &t) {
+            let mut stats = Stats::default();
+            let res = path::pathificate_with_stats(aid, cfg, &config.configuration, &t, &mut stats);
+            if matches!(format, MessageFormat::Human) {
+                print_notes(aid, cfg, &stats, res.as_ref().ok().map(|p| p.points.len()));
+            }
+            let pf = match res {
                 Ok(p) => {
                     if is_debug {
                         println!("x,y");
@@ -145,39 +235,144 @@ fn main() {
                     }
                     p
                 },
-                Err(e) => {
-                    eprintln!("{e:#}");
-                    exit(1);
-                }
+                Err(e) => fail(&e, format)
             };
-            match path::write(&aid, config.configuration.output_directory.as_path(), &cfg, &pf) {
-                Ok(_) => (),
-                Err(e) => {
-                    eprintln!("{e:#}");
-                    exit(1);
-                }
+            let res = create_dir_all(&config.configuration.output_directory)
+                .with_context(|| Error::new(ErrorKind::Io, "Failed to create output directory"))
+                .and_then(|()| path::write(aid, config.configuration.output_directory.as_path(), cfg, &pf));
+            if let Err(e) = res {
+                fail(&e, format);
             }
         },
         Some(("minmax", m)) => {
             let aid = m.get_one::<String>("aircraft").expect("aircraft ID is required");
             let cfc = config.clone();
-            let mut cfg = config.aircraft.get_mut(aid).expect("Aircraft ID is not present in configuration");
-            minmax(aid, m, cfg, &cfc);
+            let cfg = config.aircraft.get_mut(aid).unwrap_or_else(|| fail(&not_configured(aid), format));
+            minmax(aid, &ranges(m), cfg, &cfc, format);
         },
         Some(("minmax_all", m)) => {
             let cf2 = config.clone();
-            config.aircraft.par_iter_mut().for_each(|u| {
-                minmax(u.0, m, u.1, &cf2);
-            });
-            
+            let ranges = ranges(m);
+            let mut tuned = config.aircraft.par_iter_mut()
+                .filter_map(|u| minmax(u.0, &ranges, u.1, &cf2, format).map(|_| (u.0.clone(), u.1.optimizer.clone())))
+                .collect::<Vec<_>>();
+            tuned.sort_by(|a, b| a.0.cmp(&b.0));
+
+            // only the optimizer changed, everything else is inherited or already in the configuration
             println!("[aircraft]");
-            for (id, ac) in &config.aircraft {
-                let doc = toml_edit::ser::to_document(&ac).expect("ser failed");
+            for (id, optimizer) in &tuned {
+                let doc = toml_edit::ser::to_document(optimizer)
+                    .unwrap_or_else(|e| fail(&anyhow::Error::new(e).context(format!("[{id}] failed to serialize optimizer")), format));
+                let mut entry = InlineTable::new();
+                entry.insert("optimizer", doc.as_table().clone().into_inline_table().into());
                 let mut d2 = DocumentMut::new();
-                d2.insert(id, doc.as_table().clone().into_inline_table().into());
-                print!("{}", d2.to_string());
+                d2.insert(id, entry.into());
+                print!("{d2}");
             }
-        }
+        },
+        Some(("watch", m)) => {
+            let preview = m.get_one::<PathBuf>("preview").expect("preview directory has a default");
+            let interval = Duration::from_millis(*m.get_one::<u64>("interval").expect("interval has a default"));
+            if let Err(e) = create_dir_all(preview).with_context(|| Error::new(ErrorKind::Io, "Failed to create preview directory")) {
+                fail(&e, format);
+            }
+
+            let mut watcher = Watcher::new(Path::new(config_path), config);
+            let mut ids = watcher.config().aircraft.keys().cloned().collect::<Vec<_>>();
+            ids.sort();
+            let mut last = HashMap::new();
+            loop {
+                for id in &ids {
+                    let outcome = rebuild(id, watcher.config(), preview, format);
+                    eprintln!("[{id}] {}", watch::delta(last.get(id), &outcome));
+                    last.insert(id.clone(), outcome);
+                }
+                if !ids.is_empty() {
+                    eprintln!("watching for changes...");
+                }
+
+                ids = loop {
+                    sleep(interval);
+                    match watcher.changed() {
+                        Ok(ids) if !ids.is_empty() => break ids,
+                        Ok(_) => {},
+                        Err(e) => report(&e, format)
+                    }
+                };
+            }
+        },
+        Some(("status", m)) => {
+            let source = m.get_one::<PathBuf>("source").expect("source directory is required");
+            let st = match status::status(&config, source) {
+                Ok(s) => s,
+                Err(e) => fail(&e, format)
+            };
+            print_status(&st);
+            if !st.missing.is_empty() {
+                exit(ErrorKind::Io.exit_code());
+            }
+        },
+        Some(("import-dims", m)) => {
+            let table = m.get_one::<PathBuf>("table");
+            let source = m.get_one::<PathBuf>("source").expect("source directory is required");
+            let attr = m.get_one::<String>("attr").expect("attribution is required");
+            let unit = *m.get_one::<dims::Unit>("units").expect("units are required");
+
+            let table_dims = match table {
+                Some(t) => match dims::read(t, unit) {
+                    Ok(d) => d,
+                    Err(e) => fail(&e, format)
+                },
+                None => dims::builtin().clone()
+            };
+            let doc = fs::read_to_string(config_path)
+                .with_context(|| Error::new(ErrorKind::Io, format!("error reading configuration at {config_path}")))
+                .and_then(|s| s.parse::<DocumentMut>().with_context(|| Error::new(ErrorKind::Config, format!("error parsing configuration at {config_path}"))));
+            let mut doc = match doc {
+                Ok(d) => d,
+                Err(e) => fail(&e, format)
+            };
+            let res = match import::import(&mut doc, &config, source, &table_dims, attr) {
+                Ok(r) => r,
+                Err(e) => fail(&e, format)
+            };
+
+            for typ in &res.no_dims {
+                eprintln!("[{typ}] no dimensions found, skipping");
+            }
+            for typ in &res.added {
+                eprintln!("[{typ}] added");
+            }
+
+            if m.get_flag("dry-run") {
+                print!("{doc}");
+            } else if let Err(e) = fs::write(config_path, doc.to_string())
+                .with_context(|| Error::new(ErrorKind::Io, format!("error writing configuration to {config_path}"))) {
+                fail(&e, format);
+            }
+        },
+        Some(("dims", m)) => {
+            let mut ids = match m.get_one::<String>("aircraft") {
+                Some(aid) => vec![aid],
+                None => config.aircraft.keys().collect()
+            };
+            ids.sort();
+
+            let mut failed = None;
+            for aid in ids {
+                let cfg = config.aircraft.get(aid).unwrap_or_else(|| fail(&not_configured(aid), format));
+                match dims::resolve(aid, cfg) {
+                    Ok(d) => println!("{aid}: w = {:.2} ft ({}), l = {:.2} ft ({})", d.w, d.w_source, d.l, d.l_source),
+                    Err(e) => {
+                        report(&e, format);
+                        failed = Some(ErrorKind::of(&e));
+                    }
+                }
+            }
+            if let Some(kind) = failed {
+                exit(kind.exit_code());
+            }
+        },
         Some((c, _)) => {
             panic!("unknown subcommand {c}")
         },
@@ -185,73 +380,248 @@ fn main() {
     }
 }
 
+#[derive(Copy, Clone)]
+enum MessageFormat {
+    Human,
+    Json
+}
+
+/// Print a failure to stderr, or as a JSON diagnostic to stdout
+fn report(e: &anyhow::Error, format: MessageFormat) {
+    match format {
+        MessageFormat::Human => eprintln!("{e:#}"),
+        MessageFormat::Json => match serde_json::to_string(&Diagnostic::from(e)) {
+            Ok(s) => println!("{s}"),
+            Err(_) => eprintln!("{e:#}")
+        }
+    }
+}
+
+/// Report a failure and exit with the code for its kind
+fn fail(e: &anyhow::Error, format: MessageFormat) -> ! {
+    report(e, format);
+    exit(ErrorKind::of(e).exit_code());
+}
+
+fn not_configured(aid: &str) -> anyhow::Error {
+    Error { kind: ErrorKind::Config, aircraft: Some(aid.to_string()), file: None, message: "not present in configuration".to_string() }.into()
+}
+
+/// The main configuration and one derived from it for each profile to build, all of them unless `only`
+/// names some, in which case the main output is skipped
+fn targets(config: &ProgramConfig, only: Option<Vec<String>>) -> anyhow::Result<Vec<(Option<String>, ProgramConfig)>> {
+    let mut targets = vec![];
+    if only.is_none() {
+        targets.push((None, config.clone()));
+    }
+    for name in only.unwrap_or_else(|| config.profiles.keys().cloned().collect()) {
+        let profile = config.profile(&name)?;
+        targets.push((Some(name), profile));
+    }
+    Ok(targets)
+}
+
+/// Print what a build changed, unchanged aircraft only as a count unless the output is JSON
+fn print_changes(changes: &[diff::Entry], format: MessageFormat) {
+    let mut unchanged = 0;
+    for c in changes {
+        match format {
+            MessageFormat::Json => match serde_json::to_string(c) {
+                Ok(s) => println!("{s}"),
+                Err(_) => println!("{c}")
+            },
+            MessageFormat::Human if matches!(c.change, diff::Change::Unchanged) => unchanged += 1,
+            MessageFormat::Human => println!("{c}")
+        }
+    }
+    if matches!(format, MessageFormat::Human) {
+        eprintln!("{} changed, {unchanged} unchanged", changes.len() - unchanged);
+    }
+}
+
+/// Print a human readable summary of `status` to stdout
+fn print_status(status: &status::Status) {
+    if !status.unused.is_empty() {
+        println!("{} SVG(s) are not referenced by any aircraft:", status.unused.len());
+        for p in &status.unused {
+            println!("  {}", p.display());
+        }
+    }
+    if !status.missing.is_empty() {
+        println!("{} aircraft reference files that do not exist:", status.missing.len());
+        for (typ, p) in &status.missing {
+            println!("  {typ}: {}", p.display());
+        }
+    }
+    if !status.duplicates.is_empty() {
+        println!("{} file(s) are referenced by more than one aircraft:", status.duplicates.len());
+        for (p, types) in &status.duplicates {
+            println!("  {}: {}", p.display(), types.join(", "));
+        }
+    }
+    if status.is_clean() {
+        println!("Configuration and source directory are in sync");
+    }
+}
+
+/// Show what pathificating an aircraft skipped or fixed up along the way, and its point count if it worked
+fn print_notes(aid: &str, cfg: &AircraftConfig, stats: &Stats, points: Option<usize>) {
+    let f = cfg.f.display();
+    for w in &stats.warnings {
+        eprintln!("[{aid} {f}] warning: {w}");
+    }
+    if let Some(d) = stats.rotation {
+        eprintln!("[{aid} {f}] auto orientation -> rotating {d} degrees");
+    }
+    if stats.repaired {
+        eprintln!("[{aid} {f}] repaired self-intersections");
+    }
+    if let Some(points) = points {
+        eprintln!("[{aid} {f}] pathificated -> {points} points");
+    }
+}
 
-fn minmax(aid: &str, m: &ArgMatches, cfg: &mut AircraftConfig, config: &ProgramConfig) -> Option<(f64, f64)> {
-    let afloor = m.get_one::<f64>("afloor").expect("A-floor is required");
-    let astep = m.get_one::<f64>("astep").expect("A-step is required");
-    let dfloor = m.get_one::<f64>("dfloor").expect("D-floor is required");
-    let dstep = m.get_one::<f64>("dstep").expect("D-step is required");
+/// Build one aircraft for `watch`, writing its output and preview
+fn rebuild(aid: &str, config: &ProgramConfig, preview: &Path, format: MessageFormat) -> Outcome {
+    let Some(cfg) = config.aircraft.get(aid) else {
+        return Outcome::Failed { kind: ErrorKind::Config, message: "not present in configuration".to_string() };
+    };
+    let mut stats = Stats::default();
+    let res = create_dir_all(&config.configuration.output_directory)
+        .with_context(|| Error::new(ErrorKind::Io, "Failed to create output directory"))
+        .and_then(|()| path::read(aid, cfg))
+        .and_then(|t| path::pathificate_with_stats(aid, cfg, &config.configuration, &t, &mut stats))
+        .and_then(|pf| {
+            path::write(aid, config.configuration.output_directory.as_path(), cfg, &pf)?;
+            let p = preview.join(format!("{aid}.svg"));
+            fs::write(&p, watch::preview(&pf))
+                .with_context(|| Error::aircraft(ErrorKind::Io, aid, cfg, format!("failed to write preview to {}", p.display())))?;
+            Ok(pf.points.len())
+        });
+    if matches!(format, MessageFormat::Human) {
+        print_notes(aid, cfg, &stats, None);
+    }
+    match res {
+        Ok(points) => Outcome::Built { points },
+        Err(e) => {
+            report(&e, format);
+            Outcome::Failed { kind: ErrorKind::of(&e), message: Diagnostic::from(&e).message }
+        }
+    }
+}
 
+/// An optimizer parameter for minmax to search, from `step` up to one step past `max`
+#[derive(Clone)]
+struct Range {
+    name: String,
+    max: f64,
+    step: f64
+}
 
+impl std::str::FromStr for Range {
+    type Err = anyhow::Error;
 
-    match cfg.optimizer {
-        Optimizer::ADFloor { .. } => {},
-        _ => return None
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid parameter range `{s}`, expected NAME=MAX:STEP");
+        let (name, range) = s.split_once('=').with_context(err)?;
+        let (max, step) = range.split_once(':').with_context(err)?;
+        let step: f64 = step.parse().with_context(err)?;
+        if step <= 0.0 {
+            bail!("step for `{name}` must be positive");
+        }
+        Ok(Range { name: name.to_string(), max: max.parse().with_context(err)?, step })
     }
+}
 
-    let mut af = 0.0;
-    let mut df = 0.0;
+fn tuning_args(cmd: Command) -> Command {
+    cmd
+        .arg(arg!(-p --param <RANGE> "Optimizer parameter to search, as NAME=MAX:STEP. May be repeated").action(ArgAction::Append).value_parser(|s: &str| s.parse::<Range>()))
+        .arg(arg!(-f --afloor <AFLOOR> "Maximum A-floor value, same as -p a_floor=AFLOOR:ASTEP").requires("astep").value_parser(value_parser!(f64)))
+        .arg(arg!(-s --astep <ASTEP> "A-step").requires("afloor").value_parser(value_parser!(f64)))
+        .arg(arg!(-d --dfloor <DFLOOR> "Maximum D-floor value, same as -p d_floor=DFLOOR:DSTEP").requires("dstep").value_parser(value_parser!(f64)))
+        .arg(arg!(-S --dstep <DSTEP> "D-step").requires("dfloor").value_parser(value_parser!(f64)))
+        .group(ArgGroup::new("ranges").args(["param", "afloor", "dfloor"]).multiple(true).required(true))
+}
 
-    let max_df = *dfloor;
-    let max_af = *afloor;
+fn ranges(m: &ArgMatches) -> Vec<Range> {
+    let mut ranges = m.get_many::<Range>("param").into_iter().flatten().cloned().collect::<Vec<_>>();
+    if let (Some(max), Some(step)) = (m.get_one::<f64>("afloor"), m.get_one::<f64>("astep")) {
+        ranges.push(Range { name: "a_floor".to_string(), max: *max, step: *step });
+    }
+    if let (Some(max), Some(step)) = (m.get_one::<f64>("dfloor"), m.get_one::<f64>("dstep")) {
+        ranges.push(Range { name: "d_floor".to_string(), max: *max, step: *step });
+    }
+    ranges
+}
 
-    let mut successful: Vec<(f64, f64)> = vec![];
+/// Try every combination of values in `ranges` and keep the one with the smallest sum that still builds
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
+fn minmax(aid: &str, ranges: &[Range], cfg: &mut AircraftConfig, config: &ProgramConfig, format: MessageFormat) -> Option<Vec<f64>> {
+    let Some(optimizer) = optimizer::lookup(&cfg.optimizer.t) else {
+        eprintln!("minmax: skipping {aid}, optimizer `{}` is not registered", cfg.optimizer.t);
+        return None;
+    };
+    if let Some(r) = ranges.iter().find(|r| !optimizer.params().iter().any(|p| p.name == r.name)) {
+        eprintln!("minmax: skipping {aid}, optimizer `{}` has no parameter `{}`", cfg.optimizer.t, r.name);
+        return None;
+    }
 
-    let t = match path::read(&aid, &cfg) {
+    let t = match path::read(aid, cfg) {
         Ok(r) => r,
         Err(e) => {
-            eprintln!("{e:#}");
+            report(&e, format);
             return None;
         }
     };
 
-    loop {
-        cfg.optimizer = Optimizer::ADFloor { a_floor: af + astep, d_floor: df + dstep };
+    let original = cfg.optimizer.params.clone();
+    let counts = ranges.iter().map(|r| (r.max / r.step).floor() as usize + 1).collect::<Vec<_>>();
+    let mut steps = vec![1; ranges.len()];
+    let mut best: Option<Vec<f64>> = None;
+
+    'search: loop {
+        let values = ranges.iter().zip(&steps).map(|(r, k)| *k as f64 * r.step).collect::<Vec<_>>();
+        for (r, v) in ranges.iter().zip(&values) {
+            cfg.optimizer.params.insert(r.name.clone(), *v);
+        }
 
-        eprintln!("a_floor={} d_floor={}", af, df);
+        eprintln!("{}", ranges.iter().zip(&values).map(|(r, v)| format!("{}={v}", r.name)).collect::<Vec<_>>().join(" "));
 
-        match path::pathificate(aid, cfg, config.configuration.max_points, &t) {
+        match path::pathificate(aid, cfg, &config.configuration, &t) {
             Ok(p) => {
                 eprintln!("ok p={}", p.points.len());
-                successful.push((af, df));
+                if best.as_ref().is_none_or(|b| values.iter().sum::<f64>() < b.iter().sum::<f64>()) {
+                    best = Some(values);
+                }
             },
             Err(e) => {
-                eprintln!("{e:#}");
+                report(&e, format);
             }
         }
 
-        df += *dstep;
-
-        if df > max_df {
-            eprintln!("minmax: hit df_ceil, last parameters a_floor={} d_floor={}", af - *astep, df - *dstep);
-            af += *astep;
-            df = 0.0;
-        }
-        if af > max_af {
-            eprintln!("minmax: hit af_ceil, last parameters a_floor={} d_floor={}", af - *astep, df - *dstep);
-            break;
+        // count up like an odometer, the last parameter moving fastest
+        let mut i = ranges.len();
+        loop {
+            if i == 0 {
+                break 'search;
+            }
+            i -= 1;
+            steps[i] += 1;
+            if steps[i] <= counts[i] {
+                break;
+            }
+            eprintln!("minmax: hit ceiling for {}", ranges[i].name);
+            steps[i] = 1;
         }
     }
 
     eprintln!("minmax: iteration complete");
-    let v = successful.iter().min_by(|u, y| {
-        let a = u.0 + u.1;
-        let b = y.0 + y.1;
-        a.total_cmp(&b)
-    })?;
-    eprintln!("aid={} a_floor={} d_floor={}", aid, v.0, v.1);
-
-    cfg.optimizer = Optimizer::ADFloor { a_floor: v.0 + astep, d_floor: v.1 + astep };
+    cfg.optimizer.params = original;
+    let best = best?;
+    for (r, v) in ranges.iter().zip(&best) {
+        cfg.optimizer.params.insert(r.name.clone(), *v);
+    }
+    eprintln!("aid={} {}", aid, ranges.iter().zip(&best).map(|(r, v)| format!("{}={v}", r.name)).collect::<Vec<_>>().join(" "));
 
-    Some(*v)
-}
\ No newline at end of file
+    Some(best)
+}
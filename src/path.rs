@@ -1,41 +1,84 @@
 use std::fs;
+use std::time::Instant;
 use anyhow::{bail, Context};
 use usvg::{Options, Tree};
-use crate::config::{AircraftConfig, AircraftPointFile};
-use crate::path::optimizer::optimize;
-use crate::path::points::points_on_path;
-use crate::point::P;
+use crate::config::{AircraftConfig, AircraftPointFile, ProgramConfigInner};
+use crate::dims;
+use crate::error::{Error, ErrorKind};
+use crate::path::optimizer::Configured;
+use crate::path::stats::Stats;
 
 pub mod utils;
 mod points;
-mod optimizer;
+pub mod optimizer;
+mod scale;
+pub(crate) mod ring;
+mod intersect;
+mod clean;
+mod symmetry;
+mod features;
+pub mod stats;
 
+/// Read and parse the SVG configured for an aircraft.
+///
+/// # Errors
+/// If the file can't be read or isn't a valid SVG.
 pub fn read(ac_typ: &str, config: &AircraftConfig) -> anyhow::Result<Tree> {
-    let svg_str = fs::read_to_string(&config.f)
-        .with_context(|| format!("[{}:{}] failed to read svg from {}", ac_typ, &config.f.display(), &config.f.display()))?;
-    Tree::from_str(&svg_str, &Options::default())
-        .with_context(|| format!("[{}:{}] failed to parse svg", ac_typ, &config.f.display()))
-} 
-
-pub fn pathificate(ac_typ: &str, config: &AircraftConfig, max_points: usize, svg_tree: &Tree) -> anyhow::Result<AircraftPointFile> {
-    let image_size_px = svg_tree.size();
-    let image_size_px = (f64::from(image_size_px.width()), f64::from(image_size_px.height()));
-    let ac_size_ft = (config.w, config.l);
-
-    let foot_per_px = (ac_size_ft.0 / image_size_px.0, ac_size_ft.1 / image_size_px.1);
-
-    let points = points_on_path(
-        utils::find_path(svg_tree.root())
-            .with_context(|| format!("[{}:{}] No path element could be found :( Make sure the SVG contains at least 1 path element with a solid stroke", ac_typ, &config.f.display()))?,
-        ac_typ,
-        config
-    )
-        .with_context(|| format!("[{}:{}] Failed to calculate points on path :(", ac_typ, &config.f.display()))?;
-
-    let points = points.iter()
-        .map(|u| P::from((u.x - image_size_px.0 / 2.0, u.y + image_size_px.1 / 2.0))) // map to center
-        .map(|u| P::from((u.x * foot_per_px.0, u.y * foot_per_px.1))) // map to worldspace
-        .collect::<Vec<_>>(); // turn back into a Vec<P>
+    let svg = fs::read(&config.f)
+        .with_context(|| Error::aircraft(ErrorKind::Io, ac_typ, config, format!("failed to read svg from {}", &config.f.display())))?;
+    parse(ac_typ, config, &svg)
+}
+
+/// Parse an SVG that's already in memory. `config.f` is only used in error messages.
+///
+/// # Errors
+/// If `svg` isn't a valid SVG.
+pub fn parse(ac_typ: &str, config: &AircraftConfig, svg: &[u8]) -> anyhow::Result<Tree> {
+    Tree::from_data(svg, &Options::default())
+        .with_context(|| Error::aircraft(ErrorKind::SvgParse, ac_typ, config, "failed to parse svg"))
+}
+
+/// Run the whole pipeline on a parsed SVG: find the outline, orient and scale it into feet, tidy it up and
+/// optimize it down to at most `max_points` points.
+///
+/// # Errors
+/// If no usable path is found, the outline can't be mapped as configured or it's still over the point
+/// limit after optimizing. Messages are prefixed with the aircraft type and file.
+pub fn pathificate(ac_typ: &str, config: &AircraftConfig, settings: &ProgramConfigInner, svg_tree: &Tree) -> anyhow::Result<AircraftPointFile> {
+    pathificate_with_stats(ac_typ, config, settings, svg_tree, &mut Stats::default())
+}
+
+/// [`pathificate`], recording point counts and timings of each stage in `stats` as it goes, so a failure
+/// still shows how far it got. Fidelity metrics are filled in once the outline is finished. Nothing is
+/// printed, warnings and fix-ups such as auto rotation are left in `stats` for the caller to show.
+///
+/// # Errors
+/// See [`pathificate`].
+pub fn pathificate_with_stats(ac_typ: &str, config: &AircraftConfig, settings: &ProgramConfigInner, svg_tree: &Tree, stats: &mut Stats) -> anyhow::Result<AircraftPointFile> {
+    let mut clock = Instant::now();
+    let dims = dims::resolve(ac_typ, config)?;
+    let simplifier = Configured::new(&config.optimizer)
+        .with_context(|| Error::aircraft(ErrorKind::Config, ac_typ, config, "Invalid optimizer configuration"))?;
+
+    let path = utils::find_path(ac_typ, config, svg_tree.root())
+        .with_context(|| Error::aircraft(ErrorKind::NoPath, ac_typ, config, "No path element could be found. Make sure the SVG contains at least 1 path element with a solid stroke or fill"))?;
+    stats.warnings.extend(utils::skipped(svg_tree.root()).into_iter().map(|kind| format!("skipping {kind} element, only the path is used")));
+
+    let clips = utils::clips(svg_tree.root(), path);
+    let mapping = scale::transform(ac_typ, config, &dims, svg_tree, path, &clips)?;
+    stats.rotation = mapping.rotation;
+
+    let outline = points::points_on_path(path, mapping.ts, &clips, ac_typ, config)
+        .map_err(|e| {
+            let kind = ErrorKind::of(&e);
+            e.context(Error::aircraft(kind, ac_typ, config, "Failed to calculate points on path"))
+        })?;
+    stats.record("sampled", outline.points.len(), &mut clock);
+
+    let points = scale::to_world(ac_typ, config, &dims, svg_tree, path, &mapping, &outline)?;
+    let points = ring::close(points);
+    let points = clean::clean(points, settings.epsilon);
+    stats.record("cleaned", points.len(), &mut clock);
 
     let mut pf = AircraftPointFile {
         points,
@@ -43,26 +86,76 @@ pub fn pathificate(ac_typ: &str, config: &AircraftConfig, max_points: usize, svg
         attribution: config.attr.clone()
     };
     
-    pf.points = optimize(&config.optimizer, pf.points);
+    let (original, optimized) = if let Some(symmetry) = &config.symmetric {
+        symmetry::optimize_symmetric(ac_typ, config, symmetry, &simplifier, &pf.points)?
+    } else {
+        let pinned = features::pinned(&pf.points, config.pin.as_ref());
+        let optimized = simplifier.optimize(&pf.points, &pinned);
+        (pf.points, optimized)
+    };
+    stats.record("optimized", optimized.len(), &mut clock);
+    pf.points = intersect::check(ac_typ, config, settings.intersections, &original, optimized, stats)?;
+    pf.points = ring::finish(pf.points, settings.winding, settings.close);
+    stats.record("finished", pf.points.len(), &mut clock);
+    stats.fidelity = stats::fidelity(&original, &pf.points);
 
-    if pf.points.len() > max_points {
-        bail!("[{}:{}] Too many points! {} points after optimization is above limit of {}, try increasing the a-floor or simplifying your SVG", ac_typ, &config.f.display(), pf.points.len(), max_points);
+    let max_points = config.max_points.unwrap_or(settings.max_points);
+    let vertices = ring::vertices(&pf.points);
+    if vertices > max_points {
+        bail!(Error::aircraft(ErrorKind::TooManyPoints, ac_typ, config, format!("Too many points! {vertices} points after optimization is above limit of {max_points}, try increasing the a-floor or simplifying your SVG")));
     }
 
-    eprintln!("[{} {}] pathificated -> {} points", ac_typ, &config.f.display(), pf.points.len());
-    
     Ok(pf)
 }
 
+/// Serialize a point file to the JSON Tower View expects.
+///
+/// # Errors
+/// If a point isn't finite, which JSON can't represent, or serialization otherwise fails.
+pub fn serialize(ac_typ: &str, config: &AircraftConfig, pf: &AircraftPointFile) -> anyhow::Result<String> {
+    if let Some(p) = pf.points.iter().find(|p| !p.x.is_finite() || !p.y.is_finite()) {
+        bail!(Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, format!("failed to serialize path spec, {p} is not a finite point")));
+    }
+    serde_json::to_string(&pf)
+        .with_context(|| Error::aircraft(ErrorKind::Other, ac_typ, config, "failed to serialize path spec"))
+}
+
+/// Write a point file to `<out>/<ac_typ>.json`.
+///
+/// # Errors
+/// If serialization or writing fails.
 pub fn write(ac_typ: &str, out: &std::path::Path, config: &AircraftConfig, pf: &AircraftPointFile) -> anyhow::Result<()> {
     let p = out.join(format!("{ac_typ}.json"));
 
-    fs::write(
-        &p,
-        serde_json::to_string(&pf)
-            .with_context(|| format!("[{}:{}] failed to serialize path spec", ac_typ, &config.f.display()))?
-    )
-        .with_context(|| format!("[{}:{}] failed to write path spec to {}", ac_typ, &config.f.display(), &p.display()))?;
+    fs::write(&p, serialize(ac_typ, config, pf)?)
+        .with_context(|| Error::aircraft(ErrorKind::Io, ac_typ, config, format!("failed to write path spec to {}", &p.display())))?;
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{AircraftConfig, AircraftPointFile};
+    use crate::error::{Error, ErrorKind};
+    use crate::point::P;
+    use super::serialize;
+
+    #[test]
+    fn serialize_rejects_non_finite_points() {
+        let config: AircraftConfig = toml_edit::de::from_str(r#"
+            f = "test.svg"
+            attr = ""
+            optimizer = { t = "3pt_avg", dt = 0.0 }
+        "#).expect("valid config");
+        let mut pf = AircraftPointFile {
+            points: vec![P::from((0.0, 0.0)), P::from((1.0, 0.0)), P::from((0.0, 1.0))],
+            aircraft_types: vec!["TEST".to_string()],
+            attribution: String::new()
+        };
+        assert!(serialize("TEST", &config, &pf).is_ok());
+
+        pf.points[1].x = f64::NAN;
+        let e = serialize("TEST", &config, &pf).expect_err("NaN");
+        assert_eq!(e.downcast_ref::<Error>().map(|e| e.kind), Some(ErrorKind::DegeneratePath));
+    }
+}
@@ -0,0 +1,126 @@
+use crate::point::P;
+
+/// Tidy up the raw outline before optimizing.
+///
+/// Removes points within `epsilon` feet of the previous one and points in the middle of a straight run,
+/// treating the outline as a ring so the seam between the last and first point is handled too. A closed
+/// ring stays closed.
+pub fn clean(mut points: Vec<P>, epsilon: f64) -> Vec<P> {
+    let closed = points.len() > 1 && points.first() == points.last();
+    if closed {
+        points.pop();
+    }
+
+    points = dedup(points, epsilon);
+    points = merge_collinear(points);
+
+    if closed {
+        if let Some(first) = points.first() {
+            points.push(*first);
+        }
+    }
+    points
+}
+
+fn dedup(points: Vec<P>, epsilon: f64) -> Vec<P> {
+    let mut out: Vec<P> = Vec::with_capacity(points.len());
+    for p in points {
+        if out.last().is_some_and(|l| l.distance(&p) <= epsilon) {
+            continue;
+        }
+        out.push(p);
+    }
+    // wrap-around
+    while out.len() > 1 && out[out.len() - 1].distance(&out[0]) <= epsilon {
+        out.pop();
+    }
+    out
+}
+
+/// Whether `b` lies on the straight line from `a` to `c`, between them
+fn collinear(a: P, b: P, c: P) -> bool {
+    let ab = (b.x - a.x, b.y - a.y);
+    let bc = (c.x - b.x, c.y - b.y);
+    let cross = ab.0 * bc.1 - ab.1 * bc.0;
+    let dot = ab.0 * bc.0 + ab.1 * bc.1;
+    // relative to the segment lengths, so this works at any scale
+    cross.abs() <= 1e-9 * a.distance(&b) * b.distance(&c) && dot > 0.0
+}
+
+fn merge_collinear(mut points: Vec<P>) -> Vec<P> {
+    loop {
+        let n = points.len();
+        if n < 3 {
+            return points;
+        }
+        let Some(i) = (0..n).find(|i| collinear(points[(i + n - 1) % n], points[*i], points[(i + 1) % n])) else {
+            return points;
+        };
+        points.remove(i);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use usvg::{Node, Options, Tree};
+    use crate::config::AircraftConfig;
+    use crate::path::points::points_on_path;
+    use crate::point::P;
+    use super::clean;
+
+    fn p(x: f64, y: f64) -> P {
+        P::from((x, y))
+    }
+
+    #[test]
+    fn removes_exact_and_near_duplicates() {
+        let pts = vec![p(0.0, 0.0), p(0.0, 0.0), p(1.0, 0.0), p(1.0, 0.0005), p(1.0, 1.0), p(0.0, 1.0)];
+        assert_eq!(clean(pts, 0.001), vec![p(0.0, 0.0), p(1.0, 0.0), p(1.0, 1.0), p(0.0, 1.0)]);
+    }
+
+    #[test]
+    fn removes_duplicates_across_the_seam() {
+        let pts = vec![p(0.0, 0.0), p(1.0, 0.0), p(1.0, 1.0), p(0.0, 1.0), p(0.0, 0.0005)];
+        assert_eq!(clean(pts, 0.001), vec![p(0.0, 0.0), p(1.0, 0.0), p(1.0, 1.0), p(0.0, 1.0)]);
+    }
+
+    #[test]
+    fn keeps_closed_rings_closed() {
+        let pts = vec![p(0.0, 0.0), p(1.0, 0.0), p(1.0, 0.0), p(1.0, 1.0), p(0.0, 1.0), p(0.0, 0.0)];
+        assert_eq!(clean(pts, 0.001), vec![p(0.0, 0.0), p(1.0, 0.0), p(1.0, 1.0), p(0.0, 1.0), p(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn merges_collinear_runs() {
+        let pts = vec![p(0.0, 0.0), p(1.0, 0.0), p(2.0, 0.0), p(3.0, 0.0), p(3.0, 1.0), p(3.0, 2.0), p(0.0, 2.0), p(0.0, 1.0)];
+        assert_eq!(clean(pts, 0.001), vec![p(0.0, 0.0), p(3.0, 0.0), p(3.0, 2.0), p(0.0, 2.0)]);
+    }
+
+    #[test]
+    fn keeps_spikes() {
+        // doubling back along the same line is collinear but changes the shape
+        let pts = vec![p(0.0, 0.0), p(2.0, 0.0), p(1.0, 0.0), p(1.0, 1.0)];
+        assert_eq!(clean(pts, 0.001), vec![p(0.0, 0.0), p(2.0, 0.0), p(1.0, 0.0), p(1.0, 1.0)]);
+    }
+
+    #[test]
+    fn cleans_line_segments_from_svg() {
+        let svg = r#"<svg width="10" height="10" xmlns="http://www.w3.org/2000/svg">
+            <path d="M 1 1 L 5 1 L 9 1 L 9 9 L 1 9 Z" stroke="black"/>
+        </svg>"#;
+        let tree = Tree::from_str(svg, &Options::default()).expect("valid svg");
+        let Some(Node::Path(path)) = tree.root().children().first() else { panic!("expected a path") };
+        let config: AircraftConfig = toml_edit::de::from_str(r#"
+            f = "test.svg"
+            attr = ""
+            optimizer = { t = "3pt_avg", dt = 0.0 }
+        "#).expect("valid config");
+
+        let raw = points_on_path(path, usvg::Transform::identity(), &[], "TEST", &config).expect("points").points;
+        assert!(raw.len() > 5, "LineTo points are pushed twice");
+
+        // (5, 1) sits on the line between (1, 1) and (9, 1), which only meet across the seam
+        let cleaned = clean(raw, 0.001);
+        assert_eq!(cleaned, vec![p(9.0, -1.0), p(9.0, -9.0), p(1.0, -9.0), p(1.0, -1.0)]);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::config::Pin;
+use crate::point::P;
+
+/// Angle in radians the outline turns through at `b`, 0 for a straight line
+fn turn_angle(a: P, b: P, c: P) -> f64 {
+    let ab = (b.x - a.x, b.y - a.y);
+    let bc = (c.x - b.x, c.y - b.y);
+    let cross = ab.0 * bc.1 - ab.1 * bc.0;
+    let dot = ab.0 * bc.0 + ab.1 * bc.1;
+    cross.atan2(dot).abs()
+}
+
+/// Mark the vertices no optimizer is allowed to remove: sharp corners such as wingtips and tail tips,
+/// and the left-, right-, top- and bottom-most points that define the aircraft's extents
+pub fn pinned(points: &[P], pin: Option<&Pin>) -> Vec<bool> {
+    let mut pinned = vec![false; points.len()];
+    let Some(pin) = pin else {
+        return pinned;
+    };
+    let n = points.len();
+    if n < 3 {
+        return pinned;
+    }
+
+    let closed = points[0] == points[n - 1];
+    let threshold = pin.angle.to_radians();
+    for i in 0..n {
+        let (prev, next) = match (i, closed) {
+            (0, true) => (n - 2, 1),
+            (0, false) => continue,
+            (i, true) if i == n - 1 => (n - 2, 1),
+            (i, false) if i == n - 1 => continue,
+            (i, _) => (i - 1, i + 1)
+        };
+        if turn_angle(points[prev], points[i], points[next]) >= threshold {
+            pinned[i] = true;
+        }
+    }
+
+    if pin.extremes {
+        let extreme = |key: fn(&P) -> f64, max: bool| {
+            points.iter()
+                .enumerate()
+                .fold(None, |best: Option<(usize, f64)>, (i, u)| {
+                    let v = key(u);
+                    match best {
+                        Some((_, b)) if (max && v <= b) || (!max && v >= b) => best,
+                        _ => Some((i, v))
+                    }
+                })
+                .map(|(i, _)| i)
+        };
+        for i in [
+            extreme(|u| u.x, false),
+            extreme(|u| u.x, true),
+            extreme(|u| u.y, false),
+            extreme(|u| u.y, true)
+        ].into_iter().flatten() {
+            pinned[i] = true;
+        }
+    }
+
+    pinned
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{self, Pin};
+    use crate::path::optimizer::optimize;
+    use crate::point::P;
+    use super::pinned;
+
+    #[test]
+    fn pinned_corners_survive_optimizers() {
+        let square = [(0.0, 0.0), (2.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0), (0.0, 0.0)].map(P::from);
+        let pin: Pin = toml_edit::de::from_str("").expect("valid pin table");
+        let pins = pinned(&square, Some(&pin));
+        assert_eq!(pins, vec![true, false, true, true, true, true]);
+
+        // floors this high would leave nothing but the ends
+        for t in [r#"t = "ad_floor"
+            a_floor = 10
+            d_floor = 100"#, r#"t = "3pt_avg"
+            dt = 10"#] {
+            let optimizer: config::Optimizer = toml_edit::de::from_str(t).expect("valid optimizer table");
+            assert_eq!(optimize(&optimizer, &square, &[]).expect("optimizer").len(), 2, "{t}");
+            let kept = optimize(&optimizer, &square, &pins).expect("optimizer");
+            assert_eq!(kept, [square[0], square[2], square[3], square[4], square[5]], "{t}");
+        }
+    }
+
+    #[test]
+    fn only_sharp_turns_are_pinned() {
+        let pin: Pin = toml_edit::de::from_str("extremes = false").expect("valid pin table");
+        // turns of about 27 and 82 degrees, and open ends are never pinned
+        let bend = [(0.0, 0.0), (1.0, 0.0), (2.0, 0.5), (1.5, 2.0)].map(P::from);
+        assert_eq!(pinned(&bend, Some(&pin)), vec![false, false, true, false]);
+        assert_eq!(pinned(&bend, None), vec![false; 4]);
+    }
+}
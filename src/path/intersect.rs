@@ -0,0 +1,175 @@
+use anyhow::bail;
+use crate::config::{AircraftConfig, Intersections};
+use crate::error::{Error, ErrorKind};
+use crate::path::stats::Stats;
+use crate::point::P;
+
+/// A pair of crossing segments, as indices of their first point
+pub struct Crossing {
+    pub a: usize,
+    pub b: usize
+}
+
+fn orientation(p: P, q: P, r: P) -> f64 {
+    (q.x - p.x) * (r.y - p.y) - (q.y - p.y) * (r.x - p.x)
+}
+
+/// Whether segments p1-p2 and q1-q2 properly cross each other
+fn crosses(p1: P, p2: P, q1: P, q2: P) -> bool {
+    let d1 = orientation(q1, q2, p1);
+    let d2 = orientation(q1, q2, p2);
+    let d3 = orientation(p1, p2, q1);
+    let d4 = orientation(p1, p2, q2);
+    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0)) && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+}
+
+/// Find every pair of non-adjacent segments that cross
+pub fn find(points: &[P]) -> Vec<Crossing> {
+    let n = points.len();
+    let mut found = vec![];
+    if n < 4 {
+        return found;
+    }
+    let closed = points[0] == points[n - 1];
+    for a in 0..n - 1 {
+        for b in a + 2..n - 1 {
+            if closed && a == 0 && b == n - 2 {
+                continue; // these share the closing point
+            }
+            if crosses(points[a], points[a + 1], points[b], points[b + 1]) {
+                found.push(Crossing { a, b });
+            }
+        }
+    }
+    found
+}
+
+/// Index of each of `kept` within `original`, if `kept` is a subsequence of it
+fn subsequence_indices(original: &[P], kept: &[P]) -> Option<Vec<usize>> {
+    let mut indices = Vec::with_capacity(kept.len());
+    let mut i = 0;
+    for k in kept {
+        while i < original.len() && original[i] != *k {
+            i += 1;
+        }
+        if i == original.len() {
+            return None;
+        }
+        indices.push(i);
+        i += 1;
+    }
+    Some(indices)
+}
+
+/// Check the optimized outline for self-intersections and deal with them as configured.
+///
+/// Repairing puts back the points the optimizer removed from every crossing segment until none are left,
+/// which only works when the optimizer kept a subset of the original points. A successful repair is noted
+/// in `stats`.
+pub fn check(ac_typ: &str, config: &AircraftConfig, mode: Intersections, original: &[P], optimized: Vec<P>, stats: &mut Stats) -> anyhow::Result<Vec<P>> {
+    if matches!(mode, Intersections::Ignore) {
+        return Ok(optimized);
+    }
+
+    let mut crossings = find(&optimized);
+    if crossings.is_empty() {
+        return Ok(optimized);
+    }
+
+    if matches!(mode, Intersections::Repair) {
+        let Some(mut indices) = subsequence_indices(original, &optimized) else {
+            let c = &crossings[0];
+            bail!(Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, format!("Outline intersects itself and could not be repaired, the optimizer output is not a subset of the outline: {} - {} crosses {} - {}", optimized[c.a], optimized[c.a + 1], optimized[c.b], optimized[c.b + 1])));
+        };
+        loop {
+            let mut reinsert = vec![];
+            for c in &crossings {
+                for s in [c.a, c.b] {
+                    reinsert.extend(indices[s] + 1..indices[s + 1]);
+                }
+            }
+            if reinsert.is_empty() {
+                break;
+            }
+            indices.extend(reinsert);
+            indices.sort_unstable();
+            indices.dedup();
+
+            let repaired = indices.iter().map(|i| original[*i]).collect::<Vec<_>>();
+            crossings = find(&repaired);
+            if crossings.is_empty() {
+                stats.repaired = true;
+                return Ok(repaired);
+            }
+        }
+        let c = &crossings[0];
+        let pts = indices.iter().map(|i| original[*i]).collect::<Vec<_>>();
+        bail!(Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, format!("Outline intersects itself and could not be repaired, the source path crosses itself: {} - {} crosses {} - {}", pts[c.a], pts[c.a + 1], pts[c.b], pts[c.b + 1])));
+    }
+
+    let c = &crossings[0];
+    bail!(Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, format!("Outline intersects itself in {} place(s), first: {} - {} crosses {} - {}, try lowering the a-floor/d-floor or setting `intersections = \"repair\"`", crossings.len(), optimized[c.a], optimized[c.a + 1], optimized[c.b], optimized[c.b + 1])));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{AircraftConfig, Intersections};
+    use crate::error::{Error, ErrorKind};
+    use crate::path::stats::Stats;
+    use crate::point::P;
+    use super::{check, find};
+
+    fn config() -> AircraftConfig {
+        toml_edit::de::from_str(r#"
+            f = "test.svg"
+            attr = ""
+            optimizer = { t = "3pt_avg", dt = 0.0 }
+        "#).expect("valid config")
+    }
+
+    fn ring(points: &[(f64, f64)]) -> Vec<P> {
+        points.iter().copied().map(P::from).collect()
+    }
+
+    #[test]
+    fn bow_tie_fails() {
+        let bow_tie = ring(&[(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0), (0.0, 0.0)]);
+        assert_eq!(find(&bow_tie).len(), 1);
+
+        let e = check("TEST", &config(), Intersections::Fail, &bow_tie, bow_tie.clone(), &mut Stats::default()).expect_err("crossing");
+        let kind = e.downcast_ref::<Error>().map(|e| e.kind);
+        assert_eq!(kind, Some(ErrorKind::DegeneratePath));
+        // it's in the source too, so there's nothing to put back
+        assert!(check("TEST", &config(), Intersections::Repair, &bow_tie, bow_tie.clone(), &mut Stats::default()).is_err());
+        assert_eq!(check("TEST", &config(), Intersections::Ignore, &bow_tie, bow_tie.clone(), &mut Stats::default()).expect("ignored"), bow_tie);
+    }
+
+    #[test]
+    fn repair_restores_removed_vertex() {
+        // a square with a notch cut in from the left, the corner at (4, 4) keeps the top edge clear of it
+        let original = ring(&[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0), (0.0, 3.0), (3.0, 3.0), (3.0, 0.5), (0.0, 0.5), (0.0, 0.0)]);
+        assert!(find(&original).is_empty());
+
+        let mut optimized = original.clone();
+        optimized.remove(2);
+        assert!(!find(&optimized).is_empty());
+
+        assert!(check("TEST", &config(), Intersections::Fail, &original, optimized.clone(), &mut Stats::default()).is_err());
+        let mut stats = Stats::default();
+        assert_eq!(check("TEST", &config(), Intersections::Repair, &original, optimized, &mut stats).expect("repaired"), original);
+        assert!(stats.repaired);
+    }
+
+    #[test]
+    fn repair_needs_a_subset_of_the_outline() {
+        let original = ring(&[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0), (0.0, 0.0)]);
+        // a moved point, as symmetric optimizing can produce
+        let moved = ring(&[(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.1), (0.0, 0.0)]);
+
+        let e = check("TEST", &config(), Intersections::Repair, &original, moved, &mut Stats::default()).expect_err("crossing");
+        let e = e.downcast_ref::<Error>().expect("aircraft error");
+        assert_eq!(e.kind, ErrorKind::DegeneratePath);
+        assert!(e.message.contains("not a subset"));
+        assert!(!e.message.contains("intersections"));
+    }
+}
@@ -1,18 +1,228 @@
-use crate::config::Optimizer;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+use anyhow::{anyhow, bail};
+use crate::config;
 use crate::point::P;
 
 mod ad_floor;
 mod three_pt_average;
 
+/// A parameter an optimizer takes from its configuration
+pub struct Param {
+    /// Key in the optimizer's TOML table
+    pub name: &'static str,
+    /// Shown to users when the parameter is missing
+    pub description: &'static str,
+    /// Value used when the configuration leaves it out, required if `None`
+    pub default: Option<f64>
+}
 
-pub fn optimize(optimizer: &Optimizer, mut pts: Vec<P>) -> Vec<P> {
-    // deduplicate
-    pts.dedup();
+/// Parameter values for an optimizer, checked against its schema
+pub struct Params(BTreeMap<String, f64>);
 
-    pts = match optimizer {
-        Optimizer::ADFloor { a_floor, d_floor } => ad_floor::optimize(*a_floor, *d_floor, pts),
-        Optimizer::ThreePointAverage { dt } => three_pt_average::optimize(*dt, pts),
-    };
+impl Params {
+    /// Value of a parameter from the optimizer's schema.
+    ///
+    /// # Panics
+    /// If `name` isn't in the schema, which is a bug in the optimizer.
+    #[must_use]
+    pub fn get(&self, name: &str) -> f64 {
+        *self.0.get(name).unwrap_or_else(|| panic!("optimizer parameter `{name}` is not in its schema"))
+    }
+}
 
-    pts
-}
\ No newline at end of file
+/// An outline simplification algorithm, selected in configuration by its name as `t`.
+///
+/// Implement this and [`register`] it to make a custom optimizer available to `config.toml` and `minmax`.
+pub trait Optimizer: Send + Sync {
+    /// Tag used as `t` in configuration
+    fn name(&self) -> &'static str;
+
+    /// Parameters taken from the rest of the optimizer's table
+    fn params(&self) -> &'static [Param];
+
+    /// Simplify an open run of points. The first and last points must be kept.
+    fn optimize(&self, params: &Params, pts: &[P]) -> Vec<P>;
+
+    /// Simplify the outline, never removing points marked in `pinned`. `pinned` may be shorter than `pts`,
+    /// in which case the remaining points aren't pinned.
+    ///
+    /// By default the outline is split at pinned points and each run between them is optimized on its own.
+    fn optimize_pinned(&self, params: &Params, pts: &[P], pinned: &[bool]) -> Vec<P> {
+        if pts.len() < 2 {
+            return pts.to_vec();
+        }
+        let mut cuts = vec![0];
+        cuts.extend((1..pts.len() - 1).filter(|&i| pinned.get(i).copied().unwrap_or(false)));
+        cuts.push(pts.len() - 1);
+
+        let mut out: Vec<P> = vec![];
+        for run in cuts.windows(2) {
+            let simplified = self.optimize(params, &pts[run[0]..=run[1]]);
+            // each run starts where the last one ended
+            let skip = usize::from(!out.is_empty());
+            out.extend(simplified.into_iter().skip(skip));
+        }
+        out
+    }
+}
+
+type Registry = RwLock<HashMap<&'static str, Arc<dyn Optimizer>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let builtin: [Arc<dyn Optimizer>; 2] = [Arc::new(ad_floor::AdFloor), Arc::new(three_pt_average::ThreePointAverage)];
+        RwLock::new(builtin.into_iter().map(|o| (o.name(), o)).collect())
+    })
+}
+
+/// Make an optimizer available under its name, replacing any registered under the same one
+pub fn register(optimizer: Arc<dyn Optimizer>) {
+    registry().write().unwrap_or_else(PoisonError::into_inner).insert(optimizer.name(), optimizer);
+}
+
+/// The optimizer registered under `name`
+#[must_use]
+pub fn lookup(name: &str) -> Option<Arc<dyn Optimizer>> {
+    registry().read().unwrap_or_else(PoisonError::into_inner).get(name).cloned()
+}
+
+/// Names of all registered optimizers, sorted
+#[must_use]
+pub fn names() -> Vec<&'static str> {
+    let mut names = registry().read().unwrap_or_else(PoisonError::into_inner).keys().copied().collect::<Vec<_>>();
+    names.sort_unstable();
+    names
+}
+
+/// An optimizer from the registry along with its checked parameters
+pub struct Configured {
+    pub optimizer: Arc<dyn Optimizer>,
+    pub params: Params
+}
+
+impl Configured {
+    /// Look up the configured optimizer and check its parameters, filling in defaults.
+    ///
+    /// # Errors
+    /// If no optimizer is registered under `t`, or a parameter is unknown or missing.
+    pub fn new(config: &config::Optimizer) -> anyhow::Result<Self> {
+        let optimizer = lookup(&config.t)
+            .ok_or_else(|| anyhow!("unknown optimizer `{}`, expected one of {}", config.t, names().join(", ")))?;
+        let schema = optimizer.params();
+
+        if let Some(k) = config.params.keys().find(|k| !schema.iter().any(|p| p.name == k.as_str())) {
+            bail!("unknown parameter `{}` for optimizer `{}`, expected {}", k, config.t, schema.iter().map(|p| p.name).collect::<Vec<_>>().join(", "));
+        }
+
+        let mut params = BTreeMap::new();
+        for p in schema {
+            let Some(v) = config.params.get(p.name).copied().or(p.default) else {
+                bail!("optimizer `{}` requires `{}` ({})", config.t, p.name, p.description);
+            };
+            params.insert(p.name.to_string(), v);
+        }
+
+        Ok(Configured { optimizer, params: Params(params) })
+    }
+
+    /// Simplify the outline, never removing points marked in `pinned`. Consecutive duplicate points are
+    /// merged first, keeping the pin if either was pinned.
+    #[must_use]
+    pub fn optimize(&self, pts: &[P], pinned: &[bool]) -> Vec<P> {
+        let (pts, pinned) = dedup(pts, pinned);
+        self.optimizer.optimize_pinned(&self.params, &pts, &pinned)
+    }
+}
+
+/// Drop consecutive duplicate points along with their pins, moving a dropped pin onto the point that's kept
+fn dedup(pts: &[P], pinned: &[bool]) -> (Vec<P>, Vec<bool>) {
+    let mut kept: Vec<P> = Vec::with_capacity(pts.len());
+    let mut pins: Vec<bool> = Vec::with_capacity(pts.len());
+    for (i, p) in pts.iter().enumerate() {
+        let pin = pinned.get(i).copied().unwrap_or(false);
+        match (kept.last(), pins.last_mut()) {
+            (Some(last), Some(last_pin)) if last == p => *last_pin |= pin,
+            _ => {
+                kept.push(*p);
+                pins.push(pin);
+            }
+        }
+    }
+    (kept, pins)
+}
+
+/// Simplify the outline with the configured optimizer, never removing points marked in `pinned`.
+/// `pinned` may be shorter than `pts`, in which case the remaining points aren't pinned.
+///
+/// # Errors
+/// If the optimizer configuration is invalid, see [`Configured::new`].
+pub fn optimize(optimizer: &config::Optimizer, pts: &[P], pinned: &[bool]) -> anyhow::Result<Vec<P>> {
+    Ok(Configured::new(optimizer)?.optimize(pts, pinned))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::config;
+    use crate::point::P;
+    use super::{register, Configured, Optimizer, Param, Params};
+
+    /// Keeps only the ends of every run
+    struct Ends;
+
+    impl Optimizer for Ends {
+        fn name(&self) -> &'static str {
+            "test_ends"
+        }
+
+        fn params(&self) -> &'static [Param] {
+            &[Param { name: "unused", description: "", default: Some(1.0) }]
+        }
+
+        fn optimize(&self, _: &Params, pts: &[P]) -> Vec<P> {
+            pts.first().into_iter().chain(pts.last()).copied().collect()
+        }
+    }
+
+    fn optimizer(toml: &str) -> anyhow::Result<Configured> {
+        let c: config::Optimizer = toml_edit::de::from_str(toml).expect("valid optimizer table");
+        Configured::new(&c)
+    }
+
+    #[test]
+    fn custom_optimizer_keeps_pinned_points() {
+        register(Arc::new(Ends));
+        let o = optimizer(r#"t = "test_ends""#).expect("registered");
+        let pts = (0..6).map(|i| P::from((f64::from(i), 0.0))).collect::<Vec<_>>();
+
+        assert_eq!(o.optimize(&pts, &[]), vec![pts[0], pts[5]]);
+        assert_eq!(o.optimize(&pts, &[false, false, true]), vec![pts[0], pts[2], pts[5]]);
+    }
+
+    #[test]
+    fn duplicates_are_merged_keeping_pins() {
+        register(Arc::new(Ends));
+        let o = optimizer(r#"t = "test_ends""#).expect("registered");
+        let pts = [0.0, 1.0, 2.0, 2.0, 3.0, 3.0].map(|x| P::from((x, 0.0)));
+
+        assert_eq!(o.optimize(&pts, &[]), vec![pts[0], pts[5]]);
+        assert_eq!(o.optimize(&pts, &[false, false, false, true]), vec![pts[0], pts[2], pts[5]]);
+    }
+
+    #[test]
+    fn builtin_parameters_are_checked() {
+        assert!(optimizer(r#"t = "ad_floor"
+            a_floor = 0.1
+            d_floor = 0"#).is_ok());
+        let missing = optimizer(r#"t = "ad_floor"
+            a_floor = 0.1"#).err().expect("d_floor is required");
+        assert!(missing.to_string().contains("d_floor"), "{missing}");
+        let unknown = optimizer(r#"t = "3pt_avg"
+            dt = 0.1
+            a_floor = 0.1"#).err().expect("a_floor is not a 3pt_avg parameter");
+        assert!(unknown.to_string().contains("a_floor"), "{unknown}");
+        assert!(optimizer(r#"t = "nope""#).is_err());
+    }
+}
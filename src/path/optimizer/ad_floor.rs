@@ -1,11 +1,40 @@
+use crate::path::optimizer::{Optimizer, Param, Params};
 use crate::point::P;
 
-pub fn optimize(a_floor: f64, d_floor: f64, pts: Vec<P>) -> Vec<P> {
+/// Drop points closer than `d_floor` to a neighbour, or where the outline turns by no more than `a_floor`
+/// radians
+pub struct AdFloor;
+
+impl Optimizer for AdFloor {
+    fn name(&self) -> &'static str {
+        "ad_floor"
+    }
+
+    fn params(&self) -> &'static [Param] {
+        &[
+            Param { name: "a_floor", description: "smallest turn in radians a point is kept for", default: None },
+            Param { name: "d_floor", description: "smallest distance in feet a point is kept at", default: None }
+        ]
+    }
+
+    fn optimize(&self, params: &Params, pts: &[P]) -> Vec<P> {
+        optimize(params.get("a_floor"), params.get("d_floor"), pts, &[])
+    }
+
+    // pinned points still take part in deciding about their neighbours
+    fn optimize_pinned(&self, params: &Params, pts: &[P], pinned: &[bool]) -> Vec<P> {
+        optimize(params.get("a_floor"), params.get("d_floor"), pts, pinned)
+    }
+}
+
+fn optimize(a_floor: f64, d_floor: f64, pts: &[P], pinned: &[bool]) -> Vec<P> {
     let mut prev_points = vec![];
-    let mut next_points = pts;
+    let mut next_points = pts.iter().copied()
+        .zip(pinned.iter().copied().chain(std::iter::repeat(false)))
+        .collect::<Vec<_>>();
     next_points.reverse(); // Flip, so I can pop points off the front
 
-    while let Some(cur) = next_points.pop() {
+    while let Some((cur, pin)) = next_points.pop() {
         if prev_points.is_empty() {
             prev_points.push(cur);
             continue;
@@ -16,8 +45,13 @@ pub fn optimize(a_floor: f64, d_floor: f64, pts: Vec<P>) -> Vec<P> {
             break; // always include the last point
         }
 
+        if pin {
+            prev_points.push(cur);
+            continue;
+        }
+
         let prev = &prev_points[prev_points.len()-1];
-        let next = &next_points[next_points.len()-1];
+        let next = &next_points[next_points.len()-1].0;
 
         // if we are very very close to the previous or next point, we can skip this one
         if cur.distance(prev) < d_floor {
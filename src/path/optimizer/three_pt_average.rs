@@ -1,11 +1,36 @@
+use crate::path::optimizer::{Optimizer, Param, Params};
 use crate::point::P;
 
-pub fn optimize(dt: f64, pts: Vec<P>) -> Vec<P> {
+/// Drop points where the outline turns by no more than `dt` radians
+pub struct ThreePointAverage;
+
+impl Optimizer for ThreePointAverage {
+    fn name(&self) -> &'static str {
+        "3pt_avg"
+    }
+
+    fn params(&self) -> &'static [Param] {
+        &[Param { name: "dt", description: "smallest turn in radians a point is kept for", default: None }]
+    }
+
+    fn optimize(&self, params: &Params, pts: &[P]) -> Vec<P> {
+        optimize(params.get("dt"), pts, &[])
+    }
+
+    // pinned points still take part in deciding about their neighbours
+    fn optimize_pinned(&self, params: &Params, pts: &[P], pinned: &[bool]) -> Vec<P> {
+        optimize(params.get("dt"), pts, pinned)
+    }
+}
+
+fn optimize(dt: f64, pts: &[P], pinned: &[bool]) -> Vec<P> {
     let mut prev_points = vec![];
-    let mut next_points = pts;
+    let mut next_points = pts.iter().copied()
+        .zip(pinned.iter().copied().chain(std::iter::repeat(false)))
+        .collect::<Vec<_>>();
     next_points.reverse(); // Flip, so I can pop points off the front
 
-    while let Some(cur) = next_points.pop() {
+    while let Some((cur, pin)) = next_points.pop() {
         if prev_points.is_empty() {
             prev_points.push(cur);
             continue;
@@ -16,8 +41,13 @@ pub fn optimize(dt: f64, pts: Vec<P>) -> Vec<P> {
             break; // always include the last point
         }
 
+        if pin {
+            prev_points.push(cur);
+            continue;
+        }
+
         let prev = &prev_points[prev_points.len()-1];
-        let next = &next_points[next_points.len()-1];
+        let next = &next_points[next_points.len()-1].0;
 
         let prev_slope = (cur.y - prev.y) / (cur.x - prev.x);
         let next_slope = (next.y - cur.y) / (next.x - cur.x);
@@ -1,35 +1,121 @@
 use anyhow::{bail, Context};
-use usvg::Path;
-use usvg::tiny_skia_path::PathSegment;
+use i_overlay::core::fill_rule::FillRule;
+use i_overlay::core::overlay_rule::OverlayRule;
+use i_overlay::float::single::SingleFloatOverlay;
+use usvg::{Path, Rect, Transform};
+use usvg::tiny_skia_path::{self, PathSegment, PathStroker};
 use crate::bezier;
 use crate::config::AircraftConfig;
+use crate::error::{Error, ErrorKind};
+use crate::path::ring::signed_area;
 use crate::point::P;
 
+/// How closely curves are followed when geometry goes through the overlay (clip paths, masks and stroke
+/// outlines), in SVG units, unless `flatness` is set
+const OVERLAY_FLATNESS: f64 = 0.1;
+
+/// The visible outline of a path, as a ring of points
+pub struct Outline {
+    /// Points with y flipped so it grows upwards
+    pub points: Vec<P>,
+    /// Bounds of what's visible, in SVG space before flipping. Taken from the path's curves unless a clip
+    /// cut it down, in which case only the clipped points are left to measure.
+    pub bounds: Rect
+}
+
+/// Turn the path (or the outline of its stroke, with `outline_stroke`) into a ring of points, cut down to
+/// what's left visible by `clips` (see [`crate::path::utils::clips`]).
 #[allow(clippy::module_name_repetitions)]
-pub fn points_on_path(path: &Path, ac_typ: &str, config: &AircraftConfig) -> anyhow::Result<Vec<P>> {
-    path.stroke().with_context(|| format!("[{}:{}] No path element could be found :( Make sure the SVG contains at least 1 path element with a solid stroke", ac_typ, &config.f.display()))?;
+pub fn points_on_path(path: &Path, ts: Transform, clips: &[Vec<tiny_skia_path::Path>], ac_typ: &str, config: &AircraftConfig) -> anyhow::Result<Outline> {
+    if let Some(flatness) = config.flatness.filter(|f| !f.is_finite() || *f <= 0.0) {
+        bail!(Error::aircraft(ErrorKind::Config, ac_typ, config, format!("`flatness` must be a positive number of SVG units, got {flatness}")));
+    }
 
-    if path.data().bounds().width() == 0.0 || path.data().bounds().height() == 0.0 {
-        bail!("Path found is a horizontal or vertical line");
+    if path.stroke().is_none() && path.fill().is_none() {
+        bail!(Error::aircraft(ErrorKind::NoPath, ac_typ, config, "No path element could be found. Make sure the SVG contains at least 1 path element with a solid stroke or fill"));
     }
 
+    let data = path.data().clone().transform(ts)
+        .with_context(|| Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, "Path could not be transformed"))?;
+
+    let (points, mut bounds) = if config.outline_stroke {
+        let points = stroke_outline(path, &data, ts, ac_typ, config)?;
+        // the stroke reaches past the centreline, so measure the outline that's actually emitted
+        let bounds = point_bounds(&points)
+            .with_context(|| Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, "Path stroke has no bounds"))?;
+        (points, bounds)
+    } else {
+        if data.bounds().width() == 0.0 || data.bounds().height() == 0.0 {
+            bail!(Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, "Path found is a horizontal or vertical line"));
+        }
+        let bounds = data.compute_tight_bounds()
+            .with_context(|| Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, "Path has no bounds"))?;
+        (sample(&data, config), bounds)
+    };
+
+    let points = if clips.is_empty() {
+        points
+    } else {
+        // clips are in canvas space, while `ts` starts from the path's own coordinates
+        let clip_ts = path.abs_transform().invert()
+            .map(|inv| ts.pre_concat(inv))
+            .with_context(|| Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, "Path transform could not be inverted"))?;
+        let layers = clips.iter()
+            .map(|layer| layer.iter()
+                .filter_map(|d| d.clone().transform(clip_ts))
+                .flat_map(|d| contours(&d, config.flatness.unwrap_or(OVERLAY_FLATNESS)))
+                .collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let visible = clip(&points, &layers)
+            .with_context(|| Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, "Path is entirely hidden by its clip path or mask"))?;
+        if visible != points {
+            bounds = point_bounds(&visible)
+                .with_context(|| Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, "Clipped path has no bounds"))?;
+        }
+        visible
+    };
+
+    Ok(Outline {
+        points: points.iter()
+            .map(|u| P::from((u.x, -u.y))) // flip to +x +y
+            .collect::<Vec<_>>(),
+        bounds
+    })
+}
+
+/// Bounds of a ring of points
+#[allow(clippy::cast_possible_truncation)]
+fn point_bounds(points: &[P]) -> Option<Rect> {
+    let (min_x, min_y, max_x, max_y) = points.iter()
+        .fold((f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY), |(x0, y0, x1, y1), u| (x0.min(u.x), y0.min(u.y), x1.max(u.x), y1.max(u.y)));
+    Rect::from_ltrb(min_x as f32, min_y as f32, max_x as f32, max_y as f32)
+}
+
+/// Sample points along the path: every vertex, plus the middle of each curve or points along it as
+/// close as `flatness` asks for
+fn sample(data: &tiny_skia_path::Path, config: &AircraftConfig) -> Vec<P> {
     let mut pos: P = P::from((0.0, 0.0));
-    let mut first: Option<P> = None;
+    // where the current subpath started, and whether it has been drawn from without closing it yet
+    let mut start: Option<P> = None;
+    let mut open = false;
 
     let mut points: Vec<P> = vec![];
 
-    for seg in path.data().segments() {
+    for seg in data.segments() {
         match seg {
             PathSegment::MoveTo(p) => {
-                if first.is_none() {
-                    first = Some(p.into());
+                if let (true, Some(start)) = (open, start) {
+                    points.push(start);
                 }
+                open = false;
+                start = Some(p.into());
                 pos = p.into();
             }
             PathSegment::LineTo(p) => {
-                if first.is_none() {
-                    first = Some(pos);
+                if start.is_none() {
+                    start = Some(pos);
                 }
+                open = true;
                 points.push(p.into());
 
                 pos = p.into();
@@ -37,46 +123,318 @@ pub fn points_on_path(path: &Path, ac_typ: &str, config: &AircraftConfig) -> any
                 points.push(p.into());
             }
             PathSegment::QuadTo(control, end) => {
-                if first.is_none() {
-                    first = Some(pos);
+                if start.is_none() {
+                    start = Some(pos);
                 }
+                open = true;
                 points.push(pos);
                 let p0 = pos;
                 let p1 = P::from(control);
                 let p2 = P::from(end);
 
-                points.push(bezier::quad(p0, p1, p2, 0.5));
+                if let Some(tolerance) = config.flatness {
+                    bezier::flatten_quad(p0, p1, p2, tolerance, &mut points);
+                } else {
+                    points.push(bezier::quad(p0, p1, p2, 0.5));
 
-                points.push(p2);
+                    points.push(p2);
+                }
 
                 pos = p2;
             }
             PathSegment::CubicTo(c1, c2, end) => {
-                if first.is_none() {
-                    first = Some(pos);
+                if start.is_none() {
+                    start = Some(pos);
                 }
+                open = true;
                 points.push(pos);
                 let p0 = pos;
                 let p1 = P::from(c1);
                 let p2 = P::from(c2);
                 let p3 = P::from(end);
 
-                points.push(bezier::cubic(p0, p1, p2, p3, 0.5));
+                if let Some(tolerance) = config.flatness {
+                    bezier::flatten_cubic(p0, p1, p2, p3, tolerance, &mut points);
+                } else {
+                    points.push(bezier::cubic(p0, p1, p2, p3, 0.5));
 
-                points.push(p3);
+                    points.push(p3);
+                }
 
                 pos = p3;
             }
             PathSegment::Close => {
+                open = false;
                 points.push(pos);
-                if let Some(pos) = first {
-                    points.push(pos);
+                if let Some(start) = start {
+                    points.push(start);
                 }
             }
         }
     }
-    
-    Ok(points.iter()
-        .map(|u| P::from((u.x, -u.y))) // flip to +x +y
-        .collect::<Vec<_>>())
-}
\ No newline at end of file
+
+    // an open subpath (e.g. a polyline) never goes back to where it started, so do that here
+    if let (true, Some(start)) = (open, start) {
+        points.push(start);
+    }
+
+    points
+}
+
+/// Outline of the path's stroke, merged with its fill if it has one, as a single ring
+fn stroke_outline(path: &Path, data: &tiny_skia_path::Path, ts: Transform, ac_typ: &str, config: &AircraftConfig) -> anyhow::Result<Vec<P>> {
+    let stroke = path.stroke()
+        .with_context(|| Error::aircraft(ErrorKind::Config, ac_typ, config, "`outline_stroke` is set, but the path has no stroke"))?;
+    let mut stroke = stroke.to_tiny_skia();
+    // gaps in a dashed stroke aren't gaps in the aircraft
+    stroke.dash = None;
+
+    // stroke in the path's own space so the width is what the SVG says
+    let outline = path.data().stroke(&stroke, PathStroker::compute_resolution_scale(&ts))
+        .and_then(|d| d.transform(ts))
+        .with_context(|| Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, "Path stroke could not be outlined"))?;
+
+    let tolerance = config.flatness.unwrap_or(OVERLAY_FLATNESS);
+    let fill = if path.fill().is_some() { contours(data, tolerance) } else { vec![] };
+    let merged = contours(&outline, tolerance).overlay(&fill, OverlayRule::Union, FillRule::NonZero);
+
+    largest_piece(merged.into_iter().flatten())
+        .with_context(|| Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, "Path stroke has no area"))
+}
+
+/// Flatten every subpath into a closed polygon
+fn contours(data: &tiny_skia_path::Path, tolerance: f64) -> Vec<Vec<[f64; 2]>> {
+    let mut contours = vec![];
+    let mut current: Vec<P> = vec![];
+    let mut pos = P::from((0.0, 0.0));
+    for seg in data.segments() {
+        match seg {
+            PathSegment::MoveTo(p) => {
+                contours.push(std::mem::take(&mut current));
+                pos = p.into();
+                current.push(pos);
+            }
+            PathSegment::LineTo(p) => {
+                pos = p.into();
+                current.push(pos);
+            }
+            PathSegment::QuadTo(c, end) => {
+                bezier::flatten_quad(pos, c.into(), end.into(), tolerance, &mut current);
+                pos = end.into();
+            }
+            PathSegment::CubicTo(c1, c2, end) => {
+                bezier::flatten_cubic(pos, c1.into(), c2.into(), end.into(), tolerance, &mut current);
+                pos = end.into();
+            }
+            PathSegment::Close => {}
+        }
+    }
+    contours.push(current);
+    contours.into_iter()
+        .filter(|c| c.len() > 2)
+        .map(|c| c.iter().map(|u| [u.x, u.y]).collect())
+        .collect()
+}
+
+/// Intersect the outline with every clip layer, keeping the largest visible piece.
+///
+/// The outline is returned untouched when nothing of it is clipped (allowing for rounding in the overlay),
+/// so art that's merely wrapped in a canvas-sized clip comes out the same as without. `None` if nothing is
+/// left at all.
+fn clip(points: &[P], layers: &[Vec<Vec<[f64; 2]>>]) -> Option<Vec<P>> {
+    let to_points = |c: &[[f64; 2]]| c.iter().map(|u| P::from((u[0], u[1]))).collect::<Vec<_>>();
+    let subject = vec![points.iter().map(|u| [u.x, u.y]).collect::<Vec<_>>()];
+
+    let mut visible = subject.clone();
+    for layer in layers {
+        visible = visible.overlay(layer, OverlayRule::Intersect, FillRule::NonZero)
+            .into_iter()
+            .flatten()
+            .collect();
+    }
+
+    let hidden: f64 = subject.overlay(&visible, OverlayRule::Difference, FillRule::NonZero)
+        .iter()
+        .flatten()
+        .map(|c| signed_area(&to_points(c)).abs())
+        .sum();
+    if hidden <= signed_area(points).abs() * 1e-6 {
+        return Some(points.to_vec());
+    }
+
+    largest_piece(visible)
+}
+
+/// The largest piece of an overlay result as a closed ring. Pieces come out counter-clockwise and holes
+/// clockwise, only the pieces matter for an outline.
+fn largest_piece(contours: impl IntoIterator<Item = Vec<[f64; 2]>>) -> Option<Vec<P>> {
+    let mut piece = contours.into_iter()
+        .map(|c| c.iter().map(|u| P::from((u[0], u[1]))).collect::<Vec<_>>())
+        .map(|c| (signed_area(&c), c))
+        .filter(|(a, _)| *a > 0.0)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))?
+        .1;
+    piece.push(piece[0]);
+    Some(piece)
+}
+
+#[cfg(test)]
+mod tests {
+    use usvg::{Options, Transform, Tree};
+    use crate::config::AircraftConfig;
+    use crate::error::{Error, ErrorKind};
+    use crate::path::utils::{clips, find_path};
+    use crate::point::P;
+    use super::{points_on_path, Outline};
+
+    fn outline(shape: &str, flatness: Option<f64>) -> Vec<P> {
+        outline_with(shape, |c| c.flatness = flatness)
+    }
+
+    fn outline_with(shape: &str, configure: impl FnOnce(&mut AircraftConfig)) -> Vec<P> {
+        try_outline(shape, configure).expect("points").points
+    }
+
+    fn try_outline(shape: &str, configure: impl FnOnce(&mut AircraftConfig)) -> anyhow::Result<Outline> {
+        let svg = format!(r#"<svg width="20" height="20" xmlns="http://www.w3.org/2000/svg">{shape}</svg>"#);
+        let tree = Tree::from_str(&svg, &Options::default()).expect("valid svg");
+        let mut config: AircraftConfig = toml_edit::de::from_str(r#"
+            f = "test.svg"
+            attr = ""
+            optimizer = { t = "3pt_avg", dt = 0.0 }
+        "#).expect("valid config");
+        configure(&mut config);
+
+        let path = find_path("TEST", &config, tree.root()).expect("path");
+        points_on_path(path, Transform::identity(), &clips(tree.root(), path), "TEST", &config)
+    }
+
+    fn assert_on_circle(points: &[P], center: P, r: f64, tolerance: f64) {
+        for p in points {
+            let d = (p.distance(&center) - r).abs();
+            assert!(d <= tolerance, "{p} is {d} off the circle");
+        }
+    }
+
+    #[test]
+    fn rect() {
+        let pts = outline(r#"<rect x="2" y="4" width="10" height="6" fill="black"/>"#, None);
+        for corner in [P::from((2.0, -4.0)), P::from((12.0, -4.0)), P::from((12.0, -10.0)), P::from((2.0, -10.0))] {
+            assert!(pts.contains(&corner), "missing {corner}");
+        }
+    }
+
+    #[test]
+    fn polygon_and_polyline() {
+        let polygon = outline(r#"<polygon points="1,1 9,1 5,9" fill="black"/>"#, None);
+        let polyline = outline(r#"<polyline points="1,1 9,1 5,9" stroke="black" fill="none"/>"#, None);
+        for p in [P::from((1.0, -1.0)), P::from((9.0, -1.0)), P::from((5.0, -9.0))] {
+            assert!(polygon.contains(&p), "polygon missing {p}");
+            assert!(polyline.contains(&p), "polyline missing {p}");
+        }
+    }
+
+    #[test]
+    fn open_subpaths_close_to_their_own_start() {
+        let pts = outline(r#"<path d="M1,1 L5,1 L5,5 M10,10 L14,10 L14,14" stroke="black" fill="none"/>"#, None);
+        let first = pts.iter().position(|u| *u == P::from((14.0, -10.0))).expect("second subpath");
+        assert_eq!(pts[first - 1], P::from((1.0, -1.0)));
+        assert_eq!(pts.last(), Some(&P::from((10.0, -10.0))));
+    }
+
+    #[test]
+    fn ellipse_is_flattened_by_curvature() {
+        let coarse = outline(r#"<circle cx="10" cy="10" r="8" fill="black"/>"#, None);
+        let fine = outline(r#"<circle cx="10" cy="10" r="8" fill="black"/>"#, Some(0.01));
+        assert!(fine.len() > coarse.len());
+        assert_on_circle(&fine, P::from((10.0, -10.0)), 8.0, 0.01);
+
+        let ellipse = outline(r#"<ellipse cx="10" cy="10" rx="8" ry="2" fill="black"/>"#, Some(0.01));
+        // the tight ends of the ellipse need more points than its flat sides
+        let ends = ellipse.iter().filter(|p| (p.x - 10.0).abs() > 6.0).count();
+        let sides = ellipse.iter().filter(|p| (p.x - 10.0).abs() < 2.0).count();
+        assert!(ends > sides, "{ends} points at the ends, {sides} on the sides");
+    }
+
+    #[test]
+    fn flatness_must_be_positive() {
+        for flatness in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let e = try_outline(r#"<circle cx="10" cy="10" r="5" fill="black"/>"#, |c| c.flatness = Some(flatness)).err().expect("invalid flatness");
+            assert_eq!(e.downcast_ref::<Error>().map(|e| e.kind), Some(ErrorKind::Config), "flatness {flatness}");
+        }
+    }
+
+    #[test]
+    fn arc() {
+        let pts = outline(r#"<path d="M 2 10 A 8 8 0 0 1 18 10 Z" stroke="black"/>"#, Some(0.01));
+        assert_on_circle(&pts, P::from((10.0, -10.0)), 8.0, 0.01);
+        assert!(pts.iter().any(|p| (p.y + 2.0).abs() < 0.01), "arc should reach the top of the circle");
+    }
+
+    #[test]
+    fn prefers_stroked_paths() {
+        let pts = outline(r#"<rect width="4" height="4" fill="black"/><rect x="10" y="10" width="4" height="4" stroke="black" fill="none"/>"#, None);
+        assert!(pts.iter().all(|p| p.x >= 10.0));
+    }
+
+    #[test]
+    fn clip_path_cuts_outline() {
+        let pts = outline(r#"<clipPath id="c"><rect x="0" y="0" width="5" height="20"/></clipPath>
+            <g clip-path="url(#c)"><rect x="2" y="4" width="10" height="6" fill="black"/></g>"#, None);
+        assert!(pts.iter().all(|p| p.x <= 5.0 + 1e-9), "{pts:?}");
+        for corner in [P::from((2.0, -4.0)), P::from((5.0, -4.0)), P::from((5.0, -10.0)), P::from((2.0, -10.0))] {
+            assert!(pts.iter().any(|p| p.distance(&corner) < 1e-6), "missing {corner}");
+        }
+    }
+
+    #[test]
+    fn clip_follows_group_transform() {
+        let pts = outline(r#"<clipPath id="c"><rect x="0" y="0" width="3" height="20"/></clipPath>
+            <g transform="translate(2 0)" clip-path="url(#c)"><rect x="0" y="4" width="10" height="6" fill="black"/></g>"#, None);
+        let max_x = pts.iter().map(|p| p.x).fold(f64::NEG_INFINITY, f64::max);
+        assert!((max_x - 3.0).abs() < 1e-6, "clip should end at x = 3 in the group's own space, got {max_x}");
+    }
+
+    #[test]
+    fn mask_cuts_outline() {
+        let pts = outline(r#"<mask id="m"><rect x="0" y="0" width="20" height="7" fill="white"/></mask>
+            <g mask="url(#m)"><rect x="2" y="4" width="10" height="6" fill="black"/></g>"#, None);
+        let lowest = pts.iter().map(|p| p.y).fold(f64::INFINITY, f64::min);
+        assert!((lowest + 7.0).abs() < 1e-6, "mask should cut the bottom off at y = 7, got {lowest}");
+    }
+
+    #[test]
+    fn canvas_sized_clip_changes_nothing() {
+        let shape = r#"<polygon points="1,1 9,1 5,9" fill="black"/>"#;
+        let clipped = outline(&format!(r#"<clipPath id="c"><rect width="20" height="20"/></clipPath><g clip-path="url(#c)">{shape}</g>"#), None);
+        assert_eq!(clipped, outline(shape, None));
+    }
+
+    #[test]
+    fn design_tool_frame_changes_nothing() {
+        // exported frames are declared after use, are stroked, and art often runs right up to their edges
+        let shape = r#"<path d="M 0 0 L 20 0 L 10 19.36 Z" fill="black"/>"#;
+        let framed = outline(&format!(r#"<g clip-path="url(#frame)">{shape}</g><defs><clipPath id="frame"><rect width="20" height="19.36" fill="white" stroke="black"/></clipPath></defs>"#), None);
+        assert_eq!(framed, outline(shape, None));
+    }
+
+    #[test]
+    fn stroke_outline_has_width() {
+        let pts = outline_with(r#"<path d="M 2 10 L 18 10" stroke="black" stroke-width="4"/>"#, |c| c.outline_stroke = true);
+        for corner in [P::from((2.0, -8.0)), P::from((18.0, -8.0)), P::from((18.0, -12.0)), P::from((2.0, -12.0))] {
+            assert!(pts.iter().any(|p| p.distance(&corner) < 1e-6), "missing {corner}");
+        }
+    }
+
+    #[test]
+    fn stroke_outline_includes_fill() {
+        let pts = outline_with(r#"<rect x="4" y="4" width="10" height="10" fill="black" stroke="black" stroke-width="2" stroke-linejoin="round"/>"#, |c| {
+            c.outline_stroke = true;
+            c.flatness = Some(0.01);
+        });
+        // the stroke grows the square by half its width, and round joins keep the corners off the diagonal
+        let (min_x, max_x) = pts.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| (lo.min(p.x), hi.max(p.x)));
+        assert!((min_x - 3.0).abs() < 1e-6 && (max_x - 15.0).abs() < 1e-6, "x runs {min_x}..{max_x}");
+        assert!(pts.iter().all(|p| p.distance(&P::from((3.0, -3.0))) > 0.25));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::config::Winding;
+use crate::point::P;
+
+/// Twice the signed area of the polygon, positive when counter-clockwise (with +y up)
+pub fn signed_area(points: &[P]) -> f64 {
+    points.iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum()
+}
+
+/// Shortest distance from `p` to the outline of a ring, which may or may not repeat its first point
+pub fn distance(p: &P, ring: &[P]) -> f64 {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| p.distance_to_segment(a, b))
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Close the ring if the SVG didn't
+pub fn close(mut points: Vec<P>) -> Vec<P> {
+    if let (Some(first), Some(last)) = (points.first(), points.last()) {
+        if first != last {
+            points.push(*first);
+        }
+    }
+    points
+}
+
+/// Reverse the ring if it runs the wrong way, keeping its first point, and drop the closing point if the
+/// output shouldn't repeat it. The optimizer's choices depend on direction, so this is left until after it
+/// has run.
+pub fn finish(points: Vec<P>, winding: Winding, close: bool) -> Vec<P> {
+    let mut points = self::close(points);
+    let ccw = signed_area(&points) > 0.0;
+    if ccw != matches!(winding, Winding::Ccw) {
+        points.reverse();
+    }
+
+    if !close && points.len() > 1 {
+        points.pop();
+    }
+    points
+}
+
+/// Number of distinct vertices, not counting a closing point that repeats the first
+pub fn vertices(points: &[P]) -> usize {
+    if points.len() > 1 && points.first() == points.last() {
+        points.len() - 1
+    } else {
+        points.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(ccw: bool) -> Vec<P> {
+        let mut points: Vec<P> = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].into_iter().map(P::from).collect();
+        if !ccw {
+            points.reverse();
+        }
+        points
+    }
+
+    #[test]
+    fn finish_winds_both_ways() {
+        for ccw in [true, false] {
+            for winding in [Winding::Ccw, Winding::Cw] {
+                for close in [true, false] {
+                    let ring = finish(square(ccw), winding, close);
+                    assert_eq!(signed_area(&ring) > 0.0, matches!(winding, Winding::Ccw));
+                    assert_eq!(finish(super::close(square(ccw)), winding, close), ring);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn finish_closes_or_opens_the_ring() {
+        let open = square(true);
+        let closed = close(open.clone());
+        assert_eq!(closed.len(), 5);
+        assert_eq!(close(closed.clone()), closed);
+
+        assert_eq!(finish(open.clone(), Winding::Ccw, true), closed);
+        assert_eq!(finish(closed.clone(), Winding::Ccw, true), closed);
+        assert_eq!(finish(closed.clone(), Winding::Ccw, false), open);
+        assert_eq!(finish(open.clone(), Winding::Ccw, false), open);
+
+        assert_eq!(vertices(&open), 4);
+        assert_eq!(vertices(&closed), 4);
+    }
+}
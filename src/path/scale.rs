@@ -0,0 +1,417 @@
+use anyhow::{bail, Context};
+use usvg::{Path, Rect, Transform, Tree};
+use usvg::tiny_skia_path::{self, Point};
+use crate::config::{AircraftConfig, Axis, Origin, Rotation, Scale};
+use crate::dims::Resolved;
+use crate::error::{Error, ErrorKind};
+use crate::path::points::{self, Outline};
+use crate::point::P;
+
+/// Transforms taking the path into the SVG space used for scaling
+pub struct Mapping {
+    /// Path data to SVG space, including orientation
+    pub ts: Transform,
+    /// Orientation alone, about the canvas centre
+    pub orient: Transform,
+    /// Degrees clockwise picked by `rotate = "auto"`
+    pub rotation: Option<f64>
+}
+
+/// Work out the transform to apply to the path data before it is turned into points
+pub fn transform(ac_typ: &str, config: &AircraftConfig, dims: &Resolved, svg_tree: &Tree, path: &Path, clips: &[Vec<tiny_skia_path::Path>]) -> anyhow::Result<Mapping> {
+    let base = match config.scale {
+        Scale::Canvas => Transform::identity(),
+        Scale::BoundingBox | Scale::Uniform { .. } => path.abs_transform()
+    };
+
+    let size = svg_tree.size();
+    let (cx, cy) = (size.width() / 2.0, size.height() / 2.0);
+
+    let mut orient = Transform::identity();
+    if config.flip_x {
+        orient = orient.post_concat(Transform::from_row(-1.0, 0.0, 0.0, 1.0, 2.0 * cx, 0.0));
+    }
+    if config.flip_y {
+        orient = orient.post_concat(Transform::from_row(1.0, 0.0, 0.0, -1.0, 0.0, 2.0 * cy));
+    }
+
+    let mut rotation = None;
+    let degrees = match config.rotate {
+        None => 0.0,
+        Some(Rotation::Degrees(d)) => d,
+        Some(Rotation::Auto(_)) => *rotation.insert(auto_rotation(dims, &points::points_on_path(path, base.post_concat(orient), clips, ac_typ, config)?))
+    };
+    if degrees != 0.0 {
+        #[allow(clippy::cast_possible_truncation)]
+        let r = Transform::from_rotate_at(degrees as f32, cx, cy);
+        orient = orient.post_concat(r);
+    }
+
+    Ok(Mapping { ts: base.post_concat(orient), orient, rotation })
+}
+
+/// Guess how far the art needs to be rotated (clockwise, in degrees) to point the nose up.
+///
+/// Whether the aircraft lies sideways is decided by comparing the path's aspect ratio with the configured
+/// wingspan and length. When that's ambiguous (wingspan and length within 15% of each other) the axis the
+/// outline is most symmetric about is taken as the fuselage. The nose is then assumed to be the narrower
+/// end of the fuselage, since the other end carries the tailplane.
+fn auto_rotation(dims: &Resolved, outline: &Outline) -> f64 {
+    let b = outline.bounds;
+    let mid = P::from((f64::from(b.x() + b.width() / 2.0), f64::from(b.y() + b.height() / 2.0)));
+    // back into SVG space, where y grows downwards
+    let points = outline.points.iter().map(|u| P::from((u.x, -u.y))).collect::<Vec<_>>();
+
+    let target = (dims.w / dims.l).ln();
+    let upright = if target.abs() > 0.15_f64.ln_1p() {
+        let aspect = (f64::from(b.width()) / f64::from(b.height())).ln();
+        (aspect - target).abs() <= (-aspect - target).abs()
+    } else {
+        let about_x = asymmetry(&points, |u| P::from((2.0 * mid.x - u.x, u.y)));
+        let about_y = asymmetry(&points, |u| P::from((u.x, 2.0 * mid.y - u.y)));
+        about_x <= about_y
+    };
+
+    if upright {
+        let (top, bottom) = end_widths(&points, |u| u.y, |u| u.x);
+        if top <= bottom { 0.0 } else { 180.0 }
+    } else {
+        let (left, right) = end_widths(&points, |u| u.x, |u| u.y);
+        if right <= left { 270.0 } else { 90.0 }
+    }
+}
+
+/// Mean distance from each mirrored point to the closest point of the original outline
+#[allow(clippy::cast_precision_loss)]
+fn asymmetry(points: &[P], mirror: impl Fn(&P) -> P) -> f64 {
+    let total: f64 = points.iter()
+        .map(|u| {
+            let m = mirror(u);
+            points.iter().map(|v| v.distance(&m)).fold(f64::INFINITY, f64::min)
+        })
+        .sum();
+    total / points.len() as f64
+}
+
+/// Width of the outline across the first and last 15% of its extent along an axis
+fn end_widths(points: &[P], along: impl Fn(&P) -> f64, across: impl Fn(&P) -> f64) -> (f64, f64) {
+    let min = points.iter().map(&along).fold(f64::INFINITY, f64::min);
+    let max = points.iter().map(&along).fold(f64::NEG_INFINITY, f64::max);
+    let band = (max - min) * 0.15;
+
+    let width = |near: &dyn Fn(f64) -> bool| {
+        let (lo, hi) = points.iter()
+            .filter(|u| near(along(u)))
+            .map(&across)
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+        (hi - lo).max(0.0)
+    };
+    (width(&|v| v <= min + band), width(&|v| v >= max - band))
+}
+
+/// Map points from (y-flipped) SVG space into world space in feet, centred on the origin
+pub fn to_world(ac_typ: &str, config: &AircraftConfig, dims: &Resolved, svg_tree: &Tree, path: &Path, mapping: &Mapping, outline: &Outline) -> anyhow::Result<Vec<P>> {
+    let foot_per_px = match &config.scale {
+        Scale::Canvas => {
+            let image_size_px = canvas_size(svg_tree, mapping);
+            (dims.w / image_size_px.0, dims.l / image_size_px.1)
+        },
+        Scale::BoundingBox => {
+            let (_, size_px) = bbox(outline);
+            (dims.w / size_px.0, dims.l / size_px.1)
+        },
+        Scale::Uniform { axis, tolerance } => {
+            let (_, size_px) = bbox(outline);
+            let (s, other_px, other_ft, other) = match axis {
+                Axis::W => (dims.w / size_px.0, size_px.1, dims.l, "length"),
+                Axis::L => (dims.l / size_px.1, size_px.0, dims.w, "wingspan")
+            };
+            let error = (other_px * s - other_ft).abs() / other_ft;
+            if error > *tolerance {
+                bail!(Error::aircraft(ErrorKind::Config, ac_typ, config, format!("Path {} is {:.2} ft when scaled uniformly, but {:.2} ft is configured ({:.1}% off, tolerance is {:.1}%)", other, other_px * s, other_ft, error * 100.0, tolerance * 100.0)));
+            }
+            (s, s)
+        }
+    };
+    if ![foot_per_px.0, foot_per_px.1].iter().all(|s| s.is_finite() && *s > 0.0) {
+        bail!(Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, "Path has no width or height to scale to the configured dimensions"));
+    }
+
+    let center = origin(ac_typ, config, svg_tree, path, mapping, outline)?;
+
+    Ok(outline.points.iter()
+        .map(|u| P::from((u.x - center.x, u.y - center.y))) // map to center
+        .map(|u| P::from((u.x * foot_per_px.0, u.y * foot_per_px.1))) // map to worldspace
+        .collect())
+}
+
+/// Point in (y-flipped) SVG space that maps to the world origin
+fn origin(ac_typ: &str, config: &AircraftConfig, svg_tree: &Tree, path: &Path, mapping: &Mapping, outline: &Outline) -> anyhow::Result<P> {
+    let origin = match (&config.origin, &config.scale) {
+        (Some(o), _) => o,
+        (None, Scale::Canvas) => &Origin::Canvas,
+        (None, Scale::BoundingBox | Scale::Uniform { .. }) => &Origin::BoundingBox
+    };
+
+    Ok(match origin {
+        Origin::Canvas => {
+            // orientation is about the canvas centre, so it doesn't move
+            let size = svg_tree.size();
+            P::from((f64::from(size.width()) / 2.0, -f64::from(size.height()) / 2.0))
+        },
+        Origin::BoundingBox => bbox(outline).0,
+        Origin::Centroid => centroid(&outline.points)
+            .with_context(|| Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, "Outline has no area, so it has no centroid"))?,
+        Origin::Nose => {
+            let (center, _) = bbox(outline);
+            let nose = outline.points.iter().map(|u| u.y).fold(f64::NEG_INFINITY, f64::max);
+            P::from((center.x, nose))
+        },
+        Origin::Point { x, y } => {
+            #[allow(clippy::cast_possible_truncation)]
+            let mut p = Point::from_xy(*x as f32, *y as f32);
+            from_canvas(ac_typ, config, path, mapping)?.map_point(&mut p);
+            P::from((f64::from(p.x), -f64::from(p.y)))
+        },
+        Origin::Marker { id } => {
+            let node = svg_tree.node_by_id(id)
+                .with_context(|| Error::aircraft(ErrorKind::Config, ac_typ, config, format!("Origin marker #{id} could not be found")))?;
+            let b = node.abs_bounding_box();
+            let mut p = Point::from_xy(b.x() + b.width() / 2.0, b.y() + b.height() / 2.0);
+            from_canvas(ac_typ, config, path, mapping)?.map_point(&mut p);
+            P::from((f64::from(p.x), -f64::from(p.y)))
+        }
+    })
+}
+
+/// Transform from canvas space into the space of the points. The points start from the path's own
+/// coordinates, which only match the canvas when neither the path nor its groups are transformed.
+fn from_canvas(ac_typ: &str, config: &AircraftConfig, path: &Path, mapping: &Mapping) -> anyhow::Result<Transform> {
+    path.abs_transform().invert()
+        .map(|inv| inv.post_concat(mapping.ts))
+        .with_context(|| Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, "Path transform could not be inverted"))
+}
+
+/// Area centroid of the polygon described by `points`
+fn centroid(points: &[P]) -> Option<P> {
+    let mut area = 0.0;
+    let mut c = P::from((0.0, 0.0));
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        let cross = a.x * b.y - b.x * a.y;
+        area += cross;
+        c = c + cross * (*a + b);
+    }
+    if area.abs() < f64::EPSILON {
+        return None;
+    }
+    Some(c * (1.0 / (3.0 * area)))
+}
+
+/// Size of the canvas after orientation
+fn canvas_size(svg_tree: &Tree, mapping: &Mapping) -> (f64, f64) {
+    let size = svg_tree.size();
+    if mapping.orient.is_identity() {
+        return (f64::from(size.width()), f64::from(size.height()));
+    }
+    Rect::from_xywh(0.0, 0.0, size.width(), size.height())
+        .and_then(|r| r.transform(mapping.orient))
+        .map_or((f64::from(size.width()), f64::from(size.height())), |r| (f64::from(r.width()), f64::from(r.height())))
+}
+
+/// Centre and size of the outline's bounding box in the same space as the points, with y flipped to match
+fn bbox(outline: &Outline) -> (P, (f64, f64)) {
+    let b = outline.bounds;
+    (
+        P::from((f64::from(b.x() + b.width() / 2.0), -f64::from(b.y() + b.height() / 2.0))),
+        (f64::from(b.width()), f64::from(b.height()))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use usvg::{Options, Transform, Tree};
+    use crate::config::AircraftConfig;
+    use crate::dims::{Resolved, Source};
+    use crate::error::{Error, ErrorKind};
+    use crate::path::points::points_on_path;
+    use crate::path::utils::{clips, find_path};
+    use crate::point::P;
+    use super::{origin, to_world, transform, Mapping};
+
+    fn config(extra: &str) -> AircraftConfig {
+        toml_edit::de::from_str(&format!(r#"
+            f = "test.svg"
+            attr = ""
+            optimizer = {{ t = "3pt_avg", dt = 0.0 }}
+            {extra}
+        "#)).expect("valid config")
+    }
+
+    #[test]
+    fn marker_follows_points_in_canvas_mode() {
+        let svg = r#"<svg width="20" height="20" xmlns="http://www.w3.org/2000/svg">
+            <g transform="translate(5 0)"><rect width="4" height="4" fill="black"/></g>
+            <circle id="gear" cx="7" cy="2" r="1" fill="none"/>
+        </svg>"#;
+        let tree = Tree::from_str(svg, &Options::default()).expect("valid svg");
+        let config = config(r#"origin = { t = "marker", id = "gear" }"#);
+
+        // canvas mode leaves the points in the path's own coordinates, where the marker sits at (2, 2)
+        let path = find_path("TEST", &config, tree.root()).expect("path");
+        let mapping = Mapping { ts: Transform::identity(), orient: Transform::identity(), rotation: None };
+        let outline = points_on_path(path, mapping.ts, &[], "TEST", &config).expect("points");
+        let o = origin("TEST", &config, &tree, path, &mapping, &outline).expect("origin");
+        assert!(o.distance(&P::from((2.0, -2.0))) < 1e-6, "{o}");
+    }
+
+    #[test]
+    fn point_follows_points_in_canvas_mode() {
+        let svg = r#"<svg width="20" height="20" xmlns="http://www.w3.org/2000/svg">
+            <g transform="translate(5 0) scale(2)"><rect width="4" height="4" fill="black"/></g>
+        </svg>"#;
+        let tree = Tree::from_str(svg, &Options::default()).expect("valid svg");
+        let config = config(r#"origin = { t = "point", x = 9, y = 4 }"#);
+
+        // (9, 4) on the canvas is (2, 2) in the rectangle's own coordinates
+        let path = find_path("TEST", &config, tree.root()).expect("path");
+        let mapping = Mapping { ts: Transform::identity(), orient: Transform::identity(), rotation: None };
+        let outline = points_on_path(path, mapping.ts, &[], "TEST", &config).expect("points");
+        let o = origin("TEST", &config, &tree, path, &mapping, &outline).expect("origin");
+        assert!(o.distance(&P::from((2.0, -2.0))) < 1e-6, "{o}");
+    }
+
+    #[test]
+    fn bbox_scaling_measures_the_clipped_outline() {
+        // only the left half of the 20 x 4 rectangle is visible
+        let svg = r#"<svg width="40" height="40" xmlns="http://www.w3.org/2000/svg">
+            <clipPath id="half"><rect width="10" height="40"/></clipPath>
+            <rect width="20" height="4" fill="black" clip-path="url(#half)"/>
+        </svg>"#;
+        let tree = Tree::from_str(svg, &Options::default()).expect("valid svg");
+        let config = config(r#"scale = { t = "bbox" }"#);
+        let dims = Resolved { w: 100.0, l: 20.0, w_source: Source::Config, l_source: Source::Config };
+
+        let path = find_path("TEST", &config, tree.root()).expect("path");
+        let mapping = Mapping { ts: path.abs_transform(), orient: Transform::identity(), rotation: None };
+        let outline = points_on_path(path, mapping.ts, &clips(tree.root(), path), "TEST", &config).expect("points");
+        let world = to_world("TEST", &config, &dims, &tree, path, &mapping, &outline).expect("world");
+
+        let (min_x, max_x) = world.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), u| (lo.min(u.x), hi.max(u.x)));
+        let (min_y, max_y) = world.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), u| (lo.min(u.y), hi.max(u.y)));
+        assert!((max_x - min_x - 100.0).abs() < 1e-3 && (min_x + 50.0).abs() < 1e-3, "{min_x} to {max_x}");
+        assert!((max_y - min_y - 20.0).abs() < 1e-3 && (min_y + 10.0).abs() < 1e-3, "{min_y} to {max_y}");
+    }
+
+    #[test]
+    fn stroked_line_scales_by_its_outline() {
+        let svg = r#"<svg width="20" height="20" xmlns="http://www.w3.org/2000/svg">
+            <path d="M 2 10 L 18 10" stroke="black" stroke-width="4"/>
+        </svg>"#;
+        let tree = Tree::from_str(svg, &Options::default()).expect("valid svg");
+        let config = config(r#"scale = { t = "bbox" }
+            outline_stroke = true"#);
+        let path = find_path("TEST", &config, tree.root()).expect("path");
+        let mapping = Mapping { ts: path.abs_transform(), orient: Transform::identity(), rotation: None };
+        let outline = points_on_path(path, mapping.ts, &[], "TEST", &config).expect("points");
+
+        let dims = Resolved { w: 32.0, l: 8.0, w_source: Source::Config, l_source: Source::Config };
+        let world = to_world("TEST", &config, &dims, &tree, path, &mapping, &outline).expect("world");
+        let max_y = world.iter().map(|u| u.y).fold(f64::NEG_INFINITY, f64::max);
+        assert!(world.iter().all(|u| u.x.is_finite() && u.y.is_finite()));
+        assert!((max_y - 4.0).abs() < 1e-3, "{max_y}");
+
+        let flat = Resolved { l: 0.0, ..dims };
+        let e = to_world("TEST", &config, &flat, &tree, path, &mapping, &outline).expect_err("no length");
+        assert_eq!(e.downcast_ref::<Error>().map(|e| e.kind), Some(ErrorKind::DegeneratePath));
+    }
+
+    #[test]
+    fn uniform_scale_checks_the_other_axis() {
+        let svg = r#"<svg width="40" height="40" xmlns="http://www.w3.org/2000/svg">
+            <rect width="20" height="4" fill="black"/>
+        </svg>"#;
+        let tree = Tree::from_str(svg, &Options::default()).expect("valid svg");
+        let config = config(r#"scale = { t = "uniform", axis = "w", tolerance = 0.05 }"#);
+        let path = find_path("TEST", &config, tree.root()).expect("path");
+        let mapping = Mapping { ts: path.abs_transform(), orient: Transform::identity(), rotation: None };
+        let outline = points_on_path(path, mapping.ts, &[], "TEST", &config).expect("points");
+
+        // 5 ft per unit makes the 4 unit length 20 ft, within 5% of 20.5 ft
+        let dims = Resolved { w: 100.0, l: 20.5, w_source: Source::Config, l_source: Source::Config };
+        let world = to_world("TEST", &config, &dims, &tree, path, &mapping, &outline).expect("within tolerance");
+        let max_y = world.iter().map(|u| u.y).fold(f64::NEG_INFINITY, f64::max);
+        assert!((max_y - 10.0).abs() < 1e-3, "{max_y}");
+
+        // but 20% short of 25 ft
+        let long = Resolved { l: 25.0, ..dims };
+        let e = to_world("TEST", &config, &long, &tree, path, &mapping, &outline).expect_err("outside tolerance");
+        assert_eq!(e.downcast_ref::<Error>().map(|e| e.kind), Some(ErrorKind::Config));
+        assert!(e.to_string().contains("20.0% off"), "{e}");
+    }
+
+    /// Nose, wings and tailplane of a 20 wide, 30 long aircraft on a 40 x 40 canvas, nose up
+    const PLANE: [(f64, f64); 17] = [
+        (20.0, 5.0), (22.0, 8.0), (22.0, 15.0), (30.0, 18.0), (30.0, 21.0), (22.0, 21.0), (22.0, 30.0), (26.0, 33.0),
+        (26.0, 35.0), (14.0, 35.0), (14.0, 33.0), (18.0, 30.0), (18.0, 21.0), (10.0, 21.0), (10.0, 18.0), (18.0, 15.0), (18.0, 8.0)
+    ];
+
+    /// Orient the plane as configured, returning the rotation picked and checking the nose ends up on top
+    fn orient(place: fn((f64, f64)) -> (f64, f64), extra: &str) -> Option<f64> {
+        let points = PLANE.map(place).iter().map(|(x, y)| format!("{x},{y}")).collect::<Vec<_>>().join(" ");
+        let svg = format!(r#"<svg width="40" height="40" xmlns="http://www.w3.org/2000/svg"><polygon points="{points}" fill="black"/></svg>"#);
+        let tree = Tree::from_str(&svg, &Options::default()).expect("valid svg");
+        let config = config(extra);
+        let dims = Resolved { w: 20.0, l: 30.0, w_source: Source::Config, l_source: Source::Config };
+
+        let path = find_path("TEST", &config, tree.root()).expect("path");
+        let mapping = transform("TEST", &config, &dims, &tree, path, &[]).expect("mapping");
+        let outline = points_on_path(path, mapping.ts, &[], "TEST", &config).expect("points");
+
+        let (x, y) = place(PLANE[0]);
+        #[allow(clippy::cast_possible_truncation)]
+        let mut nose = usvg::tiny_skia_path::Point::from_xy(x as f32, y as f32);
+        mapping.ts.map_point(&mut nose);
+        let nose = P::from((f64::from(nose.x), -f64::from(nose.y)));
+        assert!(outline.points.iter().all(|u| u.y <= nose.y + 1e-3), "nose at {nose} isn't on top ({extra})");
+        assert!((nose.x - 20.0).abs() < 1e-3, "nose at {nose} isn't centred ({extra})");
+        mapping.rotation
+    }
+
+    fn upright((x, y): (f64, f64)) -> (f64, f64) {
+        (x, y)
+    }
+
+    fn nose_right((x, y): (f64, f64)) -> (f64, f64) {
+        (40.0 - y, x)
+    }
+
+    fn nose_down((x, y): (f64, f64)) -> (f64, f64) {
+        (40.0 - x, 40.0 - y)
+    }
+
+    fn nose_left((x, y): (f64, f64)) -> (f64, f64) {
+        (y, x)
+    }
+
+    #[test]
+    fn auto_rotation_puts_the_nose_up() {
+        assert_eq!(orient(upright, r#"rotate = "auto""#), Some(0.0));
+        assert_eq!(orient(nose_right, r#"rotate = "auto""#), Some(270.0));
+        assert_eq!(orient(nose_down, r#"rotate = "auto""#), Some(180.0));
+        assert_eq!(orient(nose_left, r#"rotate = "auto""#), Some(90.0));
+        // mirrored art nose left is nose right once flipped, and rotated after the flip
+        assert_eq!(orient(nose_left, r#"rotate = "auto"
+            flip_x = true"#), Some(270.0));
+    }
+
+    #[test]
+    fn explicit_rotation_and_flips() {
+        assert_eq!(orient(upright, ""), None);
+        assert_eq!(orient(nose_right, "rotate = 270"), None);
+        assert_eq!(orient(nose_left, "rotate = 90"), None);
+        assert_eq!(orient(nose_down, "flip_y = true"), None);
+        assert_eq!(orient(nose_left, "flip_x = true
+            rotate = 270"), None);
+    }
+}
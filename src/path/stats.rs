@@ -0,0 +1,98 @@
+use std::time::Instant;
+use serde::Serialize;
+use crate::path::ring::{distance, signed_area};
+use crate::point::P;
+
+/// What each stage of [`crate::path::pathificate_with_stats`] did, and how closely the result follows the
+/// source outline
+#[derive(Serialize, Default)]
+pub struct Stats {
+    pub stages: Vec<Stage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fidelity: Option<Fidelity>,
+    /// Parts of the SVG that were ignored, such as images and text next to the path
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Degrees the art was rotated clockwise by `rotate = "auto"`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<f64>,
+    /// Whether points the optimizer removed were put back to undo self-intersections
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub repaired: bool
+}
+
+/// Points left after a stage of the pipeline, and how long it took
+#[derive(Serialize)]
+pub struct Stage {
+    pub name: &'static str,
+    pub points: usize,
+    pub ms: f64
+}
+
+/// How far the optimized outline strays from the cleaned source outline
+#[derive(Serialize)]
+pub struct Fidelity {
+    /// Largest distance from a source point to the optimized outline, in feet
+    pub max_deviation: f64,
+    /// Mean distance from a source point to the optimized outline, in feet
+    pub mean_deviation: f64,
+    /// Area of the optimized outline over that of the source
+    pub area_ratio: f64
+}
+
+impl Stats {
+    /// Record a finished stage, timed from `since`, and restart the clock for the next one
+    pub(crate) fn record(&mut self, name: &'static str, points: usize, since: &mut Instant) {
+        let now = Instant::now();
+        self.stages.push(Stage { name, points, ms: (now - *since).as_secs_f64() * 1000.0 });
+        *since = now;
+    }
+}
+
+/// Compare an optimized ring against the source ring it came from. Either may or may not repeat its first
+/// point at the end.
+#[allow(clippy::cast_precision_loss)]
+pub(crate) fn fidelity(source: &[P], optimized: &[P]) -> Option<Fidelity> {
+    if source.is_empty() || optimized.len() < 2 {
+        return None;
+    }
+
+    let deviations = source.iter()
+        .map(|u| distance(u, optimized))
+        .collect::<Vec<_>>();
+
+    let source_area = signed_area(source).abs();
+    Some(Fidelity {
+        max_deviation: deviations.iter().copied().fold(0.0, f64::max),
+        mean_deviation: deviations.iter().sum::<f64>() / deviations.len() as f64,
+        area_ratio: if source_area > 0.0 { signed_area(optimized).abs() / source_area } else { 0.0 }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::point::P;
+    use super::fidelity;
+
+    fn square(s: f64) -> Vec<P> {
+        vec![P::from((0.0, 0.0)), P::from((s, 0.0)), P::from((s, s)), P::from((0.0, s)), P::from((0.0, 0.0))]
+    }
+
+    #[test]
+    fn identical_rings_are_exact() {
+        let mut open = square(2.0);
+        open.pop();
+        let f = fidelity(&square(2.0), &open).expect("fidelity");
+        assert!(f.max_deviation.abs() < 1e-12);
+        assert!((f.area_ratio - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn cut_corner_is_measured() {
+        let source = square(2.0);
+        let cut = vec![P::from((0.0, 0.0)), P::from((2.0, 0.0)), P::from((0.0, 2.0)), P::from((0.0, 0.0))];
+        let f = fidelity(&source, &cut).expect("fidelity");
+        assert!((f.max_deviation - 2.0_f64.sqrt()).abs() < 1e-12, "{}", f.max_deviation);
+        assert!((f.area_ratio - 0.5).abs() < 1e-12);
+    }
+}
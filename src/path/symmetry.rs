@@ -0,0 +1,164 @@
+use anyhow::bail;
+use crate::config::{AircraftConfig, Symmetry};
+use crate::error::{Error, ErrorKind};
+use crate::path::features;
+use crate::path::optimizer::Configured;
+use crate::point::P;
+
+/// The longitudinal axis, taken as the middle of the outline's horizontal extent
+fn axis(points: &[P]) -> f64 {
+    let (min, max) = points.iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), u| (lo.min(u.x), hi.max(u.x)));
+    f64::midpoint(min, max)
+}
+
+fn mirror(p: P, axis: f64) -> P {
+    P::from((2.0 * axis - p.x, p.y))
+}
+
+/// Largest distance between a mirrored point and the outline, along with the offending point
+fn asymmetry(points: &[P], axis: f64) -> (f64, P) {
+    points.iter()
+        .map(|u| {
+            let m = mirror(*u, axis);
+            let d = points.windows(2)
+                .map(|s| m.distance_to_segment(&s[0], &s[1]))
+                .fold(f64::INFINITY, f64::min);
+            (d, *u)
+        })
+        .fold((0.0, P::from((axis, 0.0))), |a, b| if b.0 > a.0 { b } else { a })
+}
+
+/// Split the closed ring into the runs of points on the right of the axis, adding points where it crosses
+fn right_chains(points: &[P], axis: f64) -> Vec<Vec<P>> {
+    let mut chains = vec![];
+    let mut current: Vec<P> = vec![];
+    for s in points.windows(2) {
+        let (a, b) = (s[0], s[1]);
+        if a.x >= axis && current.last() != Some(&a) {
+            current.push(a);
+        }
+        if (a.x >= axis) != (b.x >= axis) {
+            let t = (axis - a.x) / (b.x - a.x);
+            let crossing = P::from((axis, a.y + t * (b.y - a.y)));
+            if a.x >= axis {
+                // leaving the right half
+                if current.last() != Some(&crossing) {
+                    current.push(crossing);
+                }
+                chains.push(std::mem::take(&mut current));
+            } else {
+                current.push(crossing);
+            }
+        }
+    }
+    if !current.is_empty() {
+        // the ring started on the right, so this run continues into the first one
+        if chains.is_empty() {
+            chains.push(current);
+        } else {
+            current.extend(chains.remove(0));
+            chains.insert(0, current);
+        }
+    }
+    chains.retain(|c| c.len() > 1);
+    chains
+}
+
+/// Optimize one half of a bilaterally symmetric outline and mirror it onto the other.
+///
+/// `points` must be a closed ring. Returns the mirrored source outline (for repairing later on) and the
+/// optimized one, both closed.
+pub fn optimize_symmetric(ac_typ: &str, config: &AircraftConfig, symmetry: &Symmetry, optimizer: &Configured, points: &[P]) -> anyhow::Result<(Vec<P>, Vec<P>)> {
+    let axis = axis(points);
+
+    let (deviation, at) = asymmetry(points, axis);
+    if deviation > symmetry.tolerance {
+        bail!(Error::aircraft(ErrorKind::Config, ac_typ, config, format!("Outline is not symmetric, {} is {:.2} ft away from its mirror image (tolerance is {} ft)", at, deviation, symmetry.tolerance)));
+    }
+
+    // slivers within the tolerance are just the art wobbling across the axis near the nose or tail
+    let mut chains = right_chains(points, axis);
+    chains.retain(|c| c.iter().any(|u| u.x - axis > symmetry.tolerance));
+    let [half] = chains.as_slice() else {
+        bail!(Error::aircraft(ErrorKind::DegeneratePath, ac_typ, config, format!("Outline crosses its axis of symmetry {} times, expected it to only cross at the nose and tail", chains.len() * 2)));
+    };
+
+    let source = mirror_half(half, axis);
+    let pinned = features::pinned(half, config.pin.as_ref());
+    let simplified = mirror_half(&optimizer.optimize(half, &pinned), axis);
+    Ok((source, simplified))
+}
+
+/// Join a half outline running from axis to axis with its mirror image, closing the ring
+fn mirror_half(half: &[P], axis: f64) -> Vec<P> {
+    let mut full = half.to_vec();
+    full.extend(half.iter().rev().skip(1).map(|u| mirror(*u, axis)));
+    full
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AircraftConfig;
+    use crate::error::{Error, ErrorKind};
+    use crate::path::optimizer::Configured;
+    use crate::point::P;
+    use super::{optimize_symmetric, right_chains};
+
+    fn ring(points: &[(f64, f64)]) -> Vec<P> {
+        points.iter().copied().map(P::from).collect()
+    }
+
+    fn optimize(points: &[P]) -> anyhow::Result<(Vec<P>, Vec<P>)> {
+        let config: AircraftConfig = toml_edit::de::from_str(r#"
+            f = "test.svg"
+            attr = ""
+            optimizer = { t = "3pt_avg", dt = 0.0 }
+            symmetric = { tolerance = 0.1 }
+        "#).expect("valid config");
+        let optimizer = Configured::new(&config.optimizer).expect("optimizer");
+        let symmetry = config.symmetric.as_ref().expect("symmetric");
+        optimize_symmetric("TEST", &config, symmetry, &optimizer, points)
+    }
+
+    #[test]
+    fn chains_split_at_the_axis() {
+        let square = ring(&[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0)]);
+        assert_eq!(right_chains(&square, 0.0), vec![ring(&[(0.0, -1.0), (1.0, -1.0), (1.0, 1.0), (0.0, 1.0)])]);
+
+        // starting on the right, the last run wraps around into the first
+        let rotated = ring(&[(1.0, -1.0), (1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)]);
+        assert_eq!(right_chains(&rotated, 0.0), vec![ring(&[(0.0, -1.0), (1.0, -1.0), (1.0, 1.0), (0.0, 1.0)])]);
+
+        // a notch cut in from the right past the axis splits the right half in two
+        let notched = ring(&[(-2.0, -2.0), (2.0, -2.0), (2.0, -0.5), (-1.0, -0.5), (-1.0, 0.5), (2.0, 0.5), (2.0, 2.0), (-2.0, 2.0), (-2.0, -2.0)]);
+        assert_eq!(right_chains(&notched, 0.0), vec![
+            ring(&[(0.0, -2.0), (2.0, -2.0), (2.0, -0.5), (0.0, -0.5)]),
+            ring(&[(0.0, 0.5), (2.0, 0.5), (2.0, 2.0), (0.0, 2.0)])
+        ]);
+        let e = optimize(&notched).expect_err("two halves");
+        assert_eq!(e.downcast_ref::<Error>().map(|e| e.kind), Some(ErrorKind::DegeneratePath));
+    }
+
+    #[test]
+    fn right_half_is_mirrored() {
+        let arrow = ring(&[(0.0, 3.0), (-2.0, 0.0), (-1.0, 0.0), (-1.0, -2.0), (1.0, -2.0), (1.0, 0.0), (2.0, 0.0), (0.0, 3.0)]);
+        let (source, optimized) = optimize(&arrow).expect("symmetric");
+        for outline in [&source, &optimized] {
+            assert_eq!(outline.first(), outline.last());
+            for u in outline {
+                assert!(outline.contains(&P::from((-u.x, u.y))), "{u} has no mirror image");
+            }
+        }
+        for u in &arrow {
+            assert!(source.contains(u), "{u} is missing");
+        }
+    }
+
+    #[test]
+    fn asymmetric_outline_is_rejected() {
+        let skewed = ring(&[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 2.0), (-1.0, -1.0)]);
+        let e = optimize(&skewed).expect_err("asymmetric");
+        assert_eq!(e.downcast_ref::<Error>().map(|e| e.kind), Some(ErrorKind::Config));
+    }
+}
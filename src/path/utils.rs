@@ -1,24 +1,117 @@
 use anyhow::bail;
-use usvg::{Group, Node, Path};
+use usvg::{ClipPath, Group, Mask, Node, Path, Transform};
+use usvg::tiny_skia_path::{self, PathBuilder};
+use crate::config::AircraftConfig;
+use crate::error::{Error, ErrorKind};
 
-pub fn find_path(g: &Group) -> anyhow::Result<&Path> {
+fn collect<'a>(g: &'a Group, paths: &mut Vec<&'a Path>, skipped: &mut Vec<&'static str>) {
     for node in g.children() {
         match node {
-            Node::Group(ref group) => {
-                return find_path(group);
-            },
-            Node::Path(ref path) => {
-                if path.stroke().is_some() {
-                    return Ok(path);
-                }
-            },
-            Node::Image(_) => {
-                bail!("Images will not be supported, please use a single vector path svg :(");
-            },
-            Node::Text(_) => {
-                bail!("Text will not be supported, please use a single vector path svg :(");
-            }
+            Node::Group(ref group) => collect(group, paths, skipped),
+            Node::Path(ref path) => paths.push(path),
+            Node::Image(_) => skipped.push("image"),
+            Node::Text(_) => skipped.push("text")
         }
     }
-    bail!("No valid path could be found")
-}
\ No newline at end of file
+}
+
+/// Find the path to outline: the first one with a stroke, or failing that the first filled one.
+///
+/// Basic shapes, polylines and arcs all arrive here as paths courtesy of usvg. Images and text can't be
+/// outlined, so they're skipped, see [`skipped`].
+///
+/// # Errors
+/// If there's no stroked or filled path anywhere under `g`.
+pub fn find_path<'a>(ac_typ: &str, config: &AircraftConfig, g: &'a Group) -> anyhow::Result<&'a Path> {
+    let mut paths = vec![];
+    let mut skipped = vec![];
+    collect(g, &mut paths, &mut skipped);
+
+    let path = paths.iter().find(|p| p.stroke().is_some())
+        .or_else(|| paths.iter().find(|p| p.fill().is_some()));
+
+    let Some(path) = path else {
+        if !skipped.is_empty() {
+            bail!(Error::aircraft(ErrorKind::NoPath, ac_typ, config, format!("No valid path could be found, only {}. Images and text will not be supported, please use a single vector path svg", skipped.join(", "))));
+        }
+        bail!(Error::aircraft(ErrorKind::NoPath, ac_typ, config, "No valid path could be found"))
+    };
+
+    Ok(path)
+}
+
+/// Kinds of the elements under `g` that [`find_path`] can't outline, in document order
+#[must_use]
+pub fn skipped(g: &Group) -> Vec<&'static str> {
+    let mut skipped = vec![];
+    collect(g, &mut vec![], &mut skipped);
+    skipped
+}
+
+/// Find the groups leading down to `path`, outermost first
+fn ancestors<'a>(g: &'a Group, path: &Path, chain: &mut Vec<&'a Group>) -> bool {
+    chain.push(g);
+    for node in g.children() {
+        match node {
+            Node::Group(ref group) if ancestors(group, path, chain) => return true,
+            Node::Path(ref p) if std::ptr::eq(p.as_ref(), path) => return true,
+            _ => {}
+        }
+    }
+    chain.pop();
+    false
+}
+
+/// Every path under `g`, in canvas space
+fn shapes(g: &Group, ts: Transform, out: &mut Vec<tiny_skia_path::Path>) {
+    for node in g.children() {
+        match node {
+            Node::Group(ref group) => shapes(group, ts, out),
+            Node::Path(ref p) => out.extend(p.data().clone().transform(ts.pre_concat(p.abs_transform()))),
+            Node::Image(_) | Node::Text(_) => {}
+        }
+    }
+}
+
+fn clip_layers(clip: &ClipPath, ts: Transform, layers: &mut Vec<Vec<tiny_skia_path::Path>>) {
+    let ts = ts.pre_concat(clip.transform());
+    let mut layer = vec![];
+    shapes(clip.root(), ts, &mut layer);
+    layers.push(layer);
+    if let Some(inner) = clip.clip_path() {
+        clip_layers(inner, ts, layers);
+    }
+}
+
+fn mask_layers(mask: &Mask, ts: Transform, layers: &mut Vec<Vec<tiny_skia_path::Path>>) {
+    let mut layer = vec![];
+    shapes(mask.root(), ts, &mut layer);
+    layers.push(layer);
+    layers.push(PathBuilder::from_rect(mask.rect().to_rect()).transform(ts).into_iter().collect());
+    if let Some(inner) = mask.mask() {
+        mask_layers(inner, ts, layers);
+    }
+}
+
+/// Geometry of the clip paths and masks applied to `path` by the groups it sits in, in canvas space.
+///
+/// The visible part of the path is the part inside every layer, where each layer is the union of its
+/// shapes. Masks are treated as opaque wherever their content is painted.
+#[must_use]
+pub fn clips(g: &Group, path: &Path) -> Vec<Vec<tiny_skia_path::Path>> {
+    let mut chain = vec![];
+    ancestors(g, path, &mut chain);
+
+    let mut layers = vec![];
+    for group in chain {
+        // clip paths and masks are in the user space of the group they're applied to
+        let ts = group.abs_transform();
+        if let Some(clip) = group.clip_path() {
+            clip_layers(clip, ts, &mut layers);
+        }
+        if let Some(mask) = group.mask() {
+            mask_layers(mask, ts, &mut layers);
+        }
+    }
+    layers
+}
@@ -1,8 +1,9 @@
 use std::fmt::{Display, Formatter};
-use std::ops::{Add, Mul};
+use std::ops::{Add, Mul, Sub};
 use serde::{Deserialize, Serialize};
 use usvg::tiny_skia_path::Point;
 
+/// A point, in SVG units or feet depending on where it is in the pipeline
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct P {
     pub x: f64,
@@ -15,6 +16,13 @@ impl Add for P {
         P { x: self.x + rhs.x, y: self.y + rhs.y }
     }
 }
+impl Sub for P {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        P { x: self.x - rhs.x, y: self.y - rhs.y }
+    }
+}
 impl Mul<f64> for P {
     type Output = Self;
 
@@ -46,13 +54,26 @@ impl From<Point> for P {
     }
 }
 impl P {
+    #[must_use]
     pub fn distance(&self, other: &P) -> f64 {
         ((other.x - self.x).powi(2) + (other.y - self.y).powi(2)).sqrt()
     }
+
+    /// Shortest distance from this point to the segment a-b
+    #[must_use]
+    pub fn distance_to_segment(&self, a: &P, b: &P) -> f64 {
+        let ab = (b.x - a.x, b.y - a.y);
+        let len2 = ab.0 * ab.0 + ab.1 * ab.1;
+        if len2 == 0.0 {
+            return self.distance(a);
+        }
+        let t = (((self.x - a.x) * ab.0 + (self.y - a.y) * ab.1) / len2).clamp(0.0, 1.0);
+        self.distance(&P { x: a.x + t * ab.0, y: a.y + t * ab.1 })
+    }
 }
 
 impl Display for P {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "({}, {})", self.x, self.y)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,59 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use serde::Serialize;
+use crate::error::{Diagnostic, Error, ErrorKind};
+use crate::path::stats::Stats;
+
+/// Summary of a whole `build` as JSON, for CI to post as tables and compare between commits
+#[derive(Serialize)]
+pub struct Report {
+    pub ok: usize,
+    pub failed: usize,
+    /// Sorted by profile and aircraft type so reports diff cleanly, the main output first
+    pub aircraft: Vec<Entry>
+}
+
+/// How a single aircraft went
+#[derive(Serialize)]
+pub struct Entry {
+    pub aircraft: String,
+    /// Output profile, if this isn't the main output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Input SVG
+    pub file: PathBuf,
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Diagnostic>,
+    /// Wall time for reading, pathificating and writing, in milliseconds
+    pub ms: f64,
+    #[serde(flatten)]
+    pub stats: Stats
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    Failed
+}
+
+impl Report {
+    #[must_use]
+    pub fn new(mut aircraft: Vec<Entry>) -> Self {
+        aircraft.sort_by(|a, b| (&a.profile, &a.aircraft).cmp(&(&b.profile, &b.aircraft)));
+        let failed = aircraft.iter().filter(|e| e.status == Status::Failed).count();
+        Report { ok: aircraft.len() - failed, failed, aircraft }
+    }
+
+    /// Write the report as pretty-printed JSON.
+    ///
+    /// # Errors
+    /// If the file can't be written.
+    pub fn write(&self, file: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self).context("failed to serialize build report")?;
+        fs::write(file, json + "\n")
+            .with_context(|| Error::new(ErrorKind::Io, format!("failed to write build report to {}", file.display())))
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind as IoErrorKind;
+use std::path::Path;
+use anyhow::{bail, Context};
+use regex::Regex;
+use crate::config::{AircraftConfig, ProgramConfig};
+use crate::error::{Error, ErrorKind};
+
+/// Which aircraft to build. Each kind of criterion that's given narrows the selection down to aircraft
+/// matching at least one of its values; an empty selection matches everything.
+#[derive(Default)]
+pub struct Selection {
+    /// Aircraft IDs, or globs over them using `*` and `?`
+    pub ids: Vec<String>,
+    /// Regular expressions matched anywhere in the aircraft ID, anchor them with `^...$` for whole IDs
+    pub regexes: Vec<Regex>,
+    /// Aircraft with any of these tags
+    pub tags: Vec<String>
+}
+
+impl Selection {
+    #[must_use]
+    pub fn matches(&self, id: &str, config: &AircraftConfig) -> bool {
+        (self.ids.is_empty() || self.ids.iter().any(|p| glob(p, id)))
+            && (self.regexes.is_empty() || self.regexes.iter().any(|r| r.is_match(id)))
+            && (self.tags.is_empty() || self.tags.iter().any(|t| config.tags.contains(t)))
+    }
+
+    /// IDs of the selected aircraft, sorted.
+    ///
+    /// # Errors
+    /// If an ID without wildcards isn't configured, as that's most likely a typo.
+    pub fn apply(&self, config: &ProgramConfig) -> anyhow::Result<Vec<String>> {
+        if let Some(id) = self.ids.iter().find(|p| !p.contains(['*', '?']) && !config.aircraft.contains_key(p.as_str())) {
+            bail!(Error { kind: ErrorKind::Config, aircraft: Some(id.clone()), file: None, message: "not present in configuration".to_string() });
+        }
+        let mut ids = config.aircraft.iter()
+            .filter(|(id, a)| self.matches(id, a))
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        ids.sort();
+        Ok(ids)
+    }
+}
+
+/// Match `s` against a pattern where `*` is any run of characters and `?` any single character
+pub(crate) fn glob(pattern: &str, s: &str) -> bool {
+    let (p, s) = (pattern.chars().collect::<Vec<_>>(), s.chars().collect::<Vec<_>>());
+    let (mut pi, mut si) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while si < s.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == s[si]) {
+            pi += 1;
+            si += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, si));
+            pi += 1;
+        } else if let Some((sp, ss)) = star {
+            // let the last `*` swallow one more character
+            pi = sp + 1;
+            si = ss + 1;
+            star = Some((sp, ss + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// Hashes of everything that goes into each aircraft's output, recorded by a build so the next one can
+/// skip aircraft that haven't changed. They're kept per output directory, so each profile has its own.
+#[derive(Default)]
+pub struct Hashes(BTreeMap<String, String>);
+
+impl Hashes {
+    /// Read hashes recorded by a previous build. A missing file means nothing was recorded yet.
+    ///
+    /// # Errors
+    /// If the file exists but can't be read or parsed.
+    pub fn load(file: &Path) -> anyhow::Result<Self> {
+        let s = match fs::read_to_string(file) {
+            Ok(s) => s,
+            Err(e) if e.kind() == IoErrorKind::NotFound => return Ok(Hashes::default()),
+            Err(e) => return Err(e).with_context(|| Error::new(ErrorKind::Io, format!("failed to read build hashes from {}", file.display())))
+        };
+        serde_json::from_str(&s)
+            .map(Hashes)
+            .with_context(|| Error::new(ErrorKind::Config, format!("failed to parse build hashes from {}", file.display())))
+    }
+
+    /// # Errors
+    /// If the file can't be written.
+    pub fn save(&self, file: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(&self.0).context("failed to serialize build hashes")?;
+        fs::write(file, json + "\n")
+            .with_context(|| Error::new(ErrorKind::Io, format!("failed to write build hashes to {}", file.display())))
+    }
+
+    /// Whether the aircraft's inputs differ from the ones last recorded. Unreadable inputs count as changed.
+    #[must_use]
+    pub fn changed(&self, id: &str, config: &ProgramConfig) -> bool {
+        inputs(id, config).is_none_or(|h| self.0.get(&key(id, config)) != Some(&h))
+    }
+
+    /// Record the aircraft's current inputs as built
+    pub fn record(&mut self, id: &str, config: &ProgramConfig) {
+        match inputs(id, config) {
+            Some(h) => self.0.insert(key(id, config), h),
+            None => self.0.remove(&key(id, config))
+        };
+    }
+
+    /// Forget an aircraft so it's rebuilt next time
+    pub fn forget(&mut self, id: &str, config: &ProgramConfig) {
+        self.0.remove(&key(id, config));
+    }
+}
+
+/// The aircraft's output file, which tells apart its builds in different profiles
+fn key(id: &str, config: &ProgramConfig) -> String {
+    config.configuration.output_directory.join(id).to_string_lossy().into_owned()
+}
+
+/// Hash of the SVG, the aircraft's configuration apart from its tags, and the shared settings
+fn inputs(id: &str, config: &ProgramConfig) -> Option<String> {
+    let ac = config.aircraft.get(id)?;
+    let svg = fs::read(&ac.f).ok()?;
+    let untagged = AircraftConfig { tags: vec![], ..ac.clone() };
+
+    let mut h = Fnv::default();
+    h.write(&svg);
+    h.write(serde_json::to_string(&untagged).ok()?.as_bytes());
+    h.write(serde_json::to_string(&config.configuration).ok()?.as_bytes());
+    Some(format!("{:016x}", h.0))
+}
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` gives the same result across Rust versions
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+    use crate::config::ProgramConfig;
+    use super::{glob, Selection};
+
+    #[test]
+    fn globs() {
+        assert!(glob("B7*", "B738"));
+        assert!(glob("A3?0", "A320"));
+        assert!(glob("*", ""));
+        assert!(glob("*8", "B738"));
+        assert!(!glob("A3?0", "A3200"));
+        assert!(!glob("B7*", "A320"));
+        assert!(glob("B*3*", "B7338"));
+    }
+
+    #[test]
+    fn criteria_narrow_the_selection() {
+        let config: ProgramConfig = toml_edit::de::from_str(r#"
+            [configuration]
+            output_directory = "build"
+            max_points = 100
+
+            [aircraft]
+            B738 = { f = "b738.svg", attr = "x", optimizer = { t = "ad_floor", a_floor = 0.1, d_floor = 0 }, tags = ["narrowbody"] }
+            B772 = { f = "b772.svg", attr = "x", optimizer = { t = "ad_floor", a_floor = 0.1, d_floor = 0 }, tags = ["heavy"] }
+            C17 = { f = "c17.svg", attr = "x", optimizer = { t = "ad_floor", a_floor = 0.1, d_floor = 0 }, tags = ["heavy", "military"] }
+        "#).expect("valid configuration");
+
+        let all = Selection::default();
+        assert_eq!(all.apply(&config).expect("selection"), vec!["B738", "B772", "C17"]);
+
+        let heavy = Selection { tags: vec!["heavy".to_string()], ..Selection::default() };
+        assert_eq!(heavy.apply(&config).expect("selection"), vec!["B772", "C17"]);
+
+        let heavy_boeing = Selection { ids: vec!["B*".to_string()], ..heavy };
+        assert_eq!(heavy_boeing.apply(&config).expect("selection"), vec!["B772"]);
+
+        let regex = Selection { regexes: vec![Regex::new("^B7[37]").expect("regex")], ..Selection::default() };
+        assert_eq!(regex.apply(&config).expect("selection"), vec!["B738", "B772"]);
+
+        let typo = Selection { ids: vec!["B739".to_string()], ..Selection::default() };
+        assert!(typo.apply(&config).is_err());
+    }
+}
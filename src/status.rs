@@ -0,0 +1,104 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Context;
+use crate::config::ProgramConfig;
+use crate::error::{Error, ErrorKind};
+
+/// How the configuration and a source directory of SVGs line up
+pub struct Status {
+    pub unused: Vec<PathBuf>,
+    pub missing: Vec<(String, PathBuf)>,
+    pub duplicates: Vec<(PathBuf, Vec<String>)>
+}
+
+impl Status {
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.unused.is_empty() && self.missing.is_empty() && self.duplicates.is_empty()
+    }
+}
+
+// canonicalize where possible so `source/a.svg` and `./source/a.svg` compare equal
+fn normalize(p: &Path) -> PathBuf {
+    fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf())
+}
+
+/// Cross-reference the configured aircraft against the SVGs in `source_dir`
+///
+/// # Errors
+/// If `source_dir` can't be read.
+pub fn status(config: &ProgramConfig, source_dir: &Path) -> anyhow::Result<Status> {
+    let mut svgs = BTreeSet::new();
+    for entry in fs::read_dir(source_dir)
+        .with_context(|| Error::new(ErrorKind::Io, format!("failed to read source directory {}", source_dir.display())))? {
+        let entry = entry.with_context(|| Error::new(ErrorKind::Io, format!("failed to read source directory {}", source_dir.display())))?;
+        let p = entry.path();
+        if p.is_file() && p.extension().is_some_and(|e| e.eq_ignore_ascii_case("svg")) {
+            svgs.insert(p);
+        }
+    }
+
+    let mut missing = vec![];
+    let mut referenced: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+    for (typ, cfg) in &config.aircraft {
+        if !cfg.f.is_file() {
+            missing.push((typ.clone(), cfg.f.clone()));
+            continue;
+        }
+        referenced.entry(normalize(&cfg.f)).or_default().push(typ.clone());
+    }
+    missing.sort();
+
+    let unused = svgs.into_iter()
+        .filter(|p| !referenced.contains_key(&normalize(p)))
+        .collect();
+
+    let duplicates = referenced.into_iter()
+        .filter(|(_, types)| types.len() > 1)
+        .map(|(p, mut types)| {
+            types.sort();
+            (p, types)
+        })
+        .collect();
+
+    Ok(Status { unused, missing, duplicates })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::config::ProgramConfig;
+    use super::status;
+
+    #[test]
+    fn unused_missing_and_duplicates() {
+        let dir = std::env::temp_dir().join(format!("vn_svgp_status_{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("create source directory");
+        for f in ["a.svg", "b.svg", "c.svg", "notes.txt"] {
+            fs::write(dir.join(f), "<svg/>").expect("write source file");
+        }
+
+        let d = dir.display();
+        let config: ProgramConfig = toml_edit::de::from_str(&format!(r#"
+            [configuration]
+            output_directory = "build"
+            max_points = 100
+
+            [aircraft]
+            A = {{ f = "{d}/a.svg", attr = "x", optimizer = {{ t = "ad_floor", a_floor = 0.1, d_floor = 0 }} }}
+            B = {{ f = "{d}/b.svg", attr = "x", optimizer = {{ t = "ad_floor", a_floor = 0.1, d_floor = 0 }} }}
+            B2 = {{ f = "{d}/./b.svg", attr = "x", optimizer = {{ t = "ad_floor", a_floor = 0.1, d_floor = 0 }} }}
+            M = {{ f = "{d}/m.svg", attr = "x", optimizer = {{ t = "ad_floor", a_floor = 0.1, d_floor = 0 }} }}
+        "#)).expect("valid configuration");
+        let st = status(&config, &dir);
+        fs::remove_dir_all(&dir).expect("remove source directory");
+        let st = st.expect("status");
+
+        assert_eq!(st.unused, vec![dir.join("c.svg")]);
+        assert_eq!(st.missing, vec![("M".to_string(), dir.join("m.svg"))]);
+        assert_eq!(st.duplicates.len(), 1);
+        assert_eq!(st.duplicates[0].1, vec!["B", "B2"]);
+        assert!(!st.is_clean());
+    }
+}
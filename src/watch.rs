@@ -0,0 +1,187 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde::Serialize;
+use crate::config::{AircraftPointFile, ProgramConfig};
+use crate::error::ErrorKind;
+
+/// Polls the configuration, the files it includes and every SVG it references for changes, without any OS-specific APIs
+pub struct Watcher {
+    config_path: PathBuf,
+    config: ProgramConfig,
+    mtimes: HashMap<PathBuf, Option<SystemTime>>
+}
+
+impl Watcher {
+    /// Start watching from an already loaded configuration
+    #[must_use]
+    pub fn new(config_path: &Path, config: ProgramConfig) -> Self {
+        let mut w = Watcher { config_path: config_path.to_path_buf(), config, mtimes: HashMap::new() };
+        w.mtimes = w.files().into_iter().map(|f| { let m = mtime(&f); (f, m) }).collect();
+        w
+    }
+
+    /// The configuration as of the last successful reload
+    #[must_use]
+    pub fn config(&self) -> &ProgramConfig {
+        &self.config
+    }
+
+    /// Aircraft that need rebuilding since the last call, sorted. The configuration is reloaded if it
+    /// changed; aircraft removed from it aren't returned.
+    ///
+    /// # Errors
+    /// If the configuration changed but can't be loaded. The previous one is kept, and the change is
+    /// picked up again on the next save.
+    pub fn changed(&mut self) -> anyhow::Result<Vec<String>> {
+        let touched = self.files().into_iter()
+            .filter(|f| self.mtimes.get(f).is_none_or(|m| *m != mtime(f)))
+            .collect::<Vec<_>>();
+        for f in &touched {
+            self.mtimes.insert(f.clone(), mtime(f));
+        }
+
+        if touched.iter().any(|f| self.config.files.contains(f)) {
+            let config = ProgramConfig::load(&self.config_path)?;
+            let affected = affected(&self.config, &config, &touched);
+            self.config = config;
+            // start watching any newly referenced or included files
+            for f in self.files() {
+                self.mtimes.entry(f.clone()).or_insert_with(|| mtime(&f));
+            }
+            Ok(affected)
+        } else {
+            Ok(affected(&self.config, &self.config, &touched))
+        }
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files = self.config.files.clone();
+        if !files.contains(&self.config_path) {
+            files.push(self.config_path.clone());
+        }
+        files.extend(self.config.aircraft.values().map(|a| a.f.clone()));
+        files
+    }
+}
+
+fn mtime(f: &Path) -> Option<SystemTime> {
+    fs::metadata(f).and_then(|m| m.modified()).ok()
+}
+
+/// Aircraft in `new` whose configuration or SVG differs from `old`. A change to the shared settings
+/// affects every aircraft.
+fn affected(old: &ProgramConfig, new: &ProgramConfig, touched: &[PathBuf]) -> Vec<String> {
+    let all = !same(&old.configuration, &new.configuration);
+
+    new.aircraft.iter()
+        .filter(|(id, a)| all
+            || touched.contains(&a.f)
+            || old.aircraft.get(*id).is_none_or(|o| !same(o, *a)))
+        .map(|(id, _)| id.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Compare configuration sections without requiring `PartialEq` on every type in them
+fn same<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/// How the last build of an aircraft went
+#[derive(Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Built { points: usize },
+    Failed { kind: ErrorKind, message: String }
+}
+
+/// One line describing how an aircraft's build changed since the last one
+#[must_use]
+pub fn delta(prev: Option<&Outcome>, next: &Outcome) -> String {
+    match (prev, next) {
+        (None, Outcome::Built { points }) => format!("{points} points"),
+        (None, Outcome::Failed { message, .. }) => format!("failed: {message}"),
+        (Some(Outcome::Built { points: was }), Outcome::Built { points }) if was == points => format!("{points} points (unchanged)"),
+        (Some(Outcome::Built { points: was }), Outcome::Built { points }) => {
+            let d = i64::try_from(*points).unwrap_or(i64::MAX) - i64::try_from(*was).unwrap_or(i64::MAX);
+            format!("{was} -> {points} points ({d:+})")
+        },
+        (Some(Outcome::Failed { .. }), Outcome::Built { points }) => format!("fixed, {points} points"),
+        (Some(Outcome::Built { points }), Outcome::Failed { message, .. }) => format!("now failing (was {points} points): {message}"),
+        (Some(was), Outcome::Failed { message, .. }) if was == next => format!("still failing: {message}"),
+        (Some(Outcome::Failed { .. }), Outcome::Failed { message, .. }) => format!("failing differently: {message}")
+    }
+}
+
+/// Render a point file as a standalone SVG, nose up, to check the outline against the art
+#[must_use]
+pub fn preview(pf: &AircraftPointFile) -> String {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (0.0_f64, 0.0_f64, 0.0_f64, 0.0_f64);
+    for p in &pf.points {
+        min_x = min_x.min(p.x);
+        max_x = max_x.max(p.x);
+        min_y = min_y.min(-p.y);
+        max_y = max_y.max(-p.y);
+    }
+    let margin = (max_x - min_x).max(max_y - min_y) * 0.05;
+    let points = pf.points.iter().map(|p| format!("{},{}", p.x, -p.y)).collect::<Vec<_>>().join(" ");
+
+    format!(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">
+<title>{} ({} points)</title>
+<polygon points="{points}" fill="none" stroke="black" stroke-width="{}"/>
+</svg>
+"#, min_x - margin, min_y - margin, max_x - min_x + 2.0 * margin, max_y - min_y + 2.0 * margin,
+        pf.aircraft_types.join(", "), pf.points.len(), margin / 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ProgramConfig;
+    use crate::error::ErrorKind;
+    use super::{affected, delta, Outcome};
+
+    fn config(toml: &str) -> ProgramConfig {
+        toml_edit::de::from_str(toml).expect("valid configuration")
+    }
+
+    const BASE: &str = r#"
+        [configuration]
+        output_directory = "build"
+        max_points = 100
+
+        [aircraft.A]
+        f = "a.svg"
+        attr = "x"
+        optimizer = { t = "ad_floor", a_floor = 0.1, d_floor = 0 }
+
+        [aircraft.B]
+        f = "b.svg"
+        attr = "x"
+        optimizer = { t = "ad_floor", a_floor = 0.1, d_floor = 0 }
+    "#;
+
+    #[test]
+    fn only_affected_aircraft_rebuild() {
+        let old = config(BASE);
+        assert!(affected(&old, &old, &[]).is_empty());
+        assert_eq!(affected(&old, &old, &["b.svg".into()]), vec!["B"]);
+
+        let tuned = config(&BASE.replacen("a_floor = 0.1", "a_floor = 0.2", 1));
+        assert_eq!(affected(&old, &tuned, &[]), vec!["A"]);
+
+        let limit = config(&BASE.replace("max_points = 100", "max_points = 50"));
+        assert_eq!(affected(&old, &limit, &[]), vec!["A", "B"]);
+    }
+
+    #[test]
+    fn deltas() {
+        let built = |points| Outcome::Built { points };
+        let failed = Outcome::Failed { kind: ErrorKind::TooManyPoints, message: "too many".to_string() };
+        assert_eq!(delta(Some(&built(90)), &built(85)), "90 -> 85 points (-5)");
+        assert_eq!(delta(Some(&failed), &built(85)), "fixed, 85 points");
+        assert_eq!(delta(Some(&built(90)), &failed), "now failing (was 90 points): too many");
+        assert_eq!(delta(Some(&failed), &failed), "still failing: too many");
+    }
+}